@@ -1,7 +1,7 @@
 use std::fs;
 use std::io::{BufReader, Read};
 
-use day_03::parse_numbers;
+use day_03::SchematicParser;
 
 fn main() -> anyhow::Result<()> {
     let mut r = BufReader::new(fs::File::open("day-03/data/input.txt")?);
@@ -14,19 +14,9 @@ fn main() -> anyhow::Result<()> {
 }
 
 fn part1(input: &str) -> anyhow::Result<String> {
-    let lines = input.lines().collect::<Vec<_>>();
-    let numbers = parse_numbers(input)?;
-
-    let total = numbers
-        .iter()
-        .filter_map(|n| {
-            if n.is_adjacent_symbol(&lines) {
-                Some(n.value_as_u64())
-            } else {
-                None
-            }
-        })
-        .sum::<u64>();
+    let schematic = SchematicParser::part_numbers().parse(input)?;
+
+    let total = schematic.part_numbers().map(|n| n.value).sum::<i64>();
 
     Ok(total.to_string())
 }
@@ -1,9 +1,7 @@
 use std::fs;
 use std::io::{BufReader, Read};
 
-use itertools::Itertools;
-
-use day_03::parse_numbers;
+use day_03::SchematicParser;
 
 fn main() -> anyhow::Result<()> {
     let mut r = BufReader::new(fs::File::open("day-03/data/input.txt")?);
@@ -16,25 +14,14 @@ fn main() -> anyhow::Result<()> {
 }
 
 fn part2(input: &str) -> anyhow::Result<String> {
-    let lines = input.lines().collect::<Vec<_>>();
-    let numbers = parse_numbers(input)?;
-
-    let grouping = numbers
-        .into_iter()
-        .into_group_map_by(|number| number.gear_pos(&lines));
-
-    let mut total = 0;
-    for (gear_pos, numbers) in grouping {
-        if gear_pos.is_none() || numbers.len() == 1 {
-            // ギアで繋がりないものは除外する。
-            continue;
-        }
-
-        total += numbers
-            .into_iter()
-            .map(|n| n.value_as_u64())
-            .product::<u64>();
-    }
+    let schematic = SchematicParser::gear_ratios().parse(input)?;
+
+    // ちょうど2つの数値に隣接している '*' をギアとする。
+    let total = schematic.gears('*', 2).try_fold(0i64, |total, g| {
+        g.ratio()
+            .and_then(|ratio| total.checked_add(ratio))
+            .ok_or_else(|| anyhow::anyhow!("gear ratio is too large at {}", g.symbol.pos))
+    })?;
 
     Ok(total.to_string())
 }
@@ -1,4 +1,4 @@
-use glam::{uvec2, UVec2};
+use glam::{ivec2, uvec2, IVec2, UVec2};
 use std::cmp::{max, min};
 
 use nom::bytes::complete::take_till;
@@ -20,8 +20,7 @@ impl<'a> Number<'a> {
 
     /// シンボルと隣接しているかどうかを判定
     pub fn is_adjacent_symbol(&self, data: &[&str]) -> bool {
-        self.find_round(data, |c: char| c != '.' && c.is_ascii_punctuation())
-            .is_some()
+        self.find_round(data, is_symbol).is_some()
     }
 
     /// 隣接しているギアの位置を返す、なければNone
@@ -49,9 +48,337 @@ impl<'a> Number<'a> {
     }
 }
 
+/// 記号として扱う文字の種類
+#[derive(Debug, Clone)]
+pub enum SymbolClass {
+    /// '.' 以外の ASCII 記号
+    Punctuation,
+    /// 指定した文字のみ
+    Chars(Vec<char>),
+    /// 任意の判定関数
+    Custom(fn(char) -> bool),
+}
+
+impl SymbolClass {
+    fn contains(&self, c: char) -> bool {
+        match self {
+            SymbolClass::Punctuation => is_symbol(c),
+            SymbolClass::Chars(chars) => chars.contains(&c),
+            SymbolClass::Custom(f) => f(c),
+        }
+    }
+}
+
+/// 隣接とみなす範囲
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Adjacency {
+    /// 上下左右
+    Four,
+    /// 上下左右と斜め
+    Eight,
+    /// マンハッタン距離が指定値以内
+    Manhattan(u32),
+}
+
+impl Adjacency {
+    /// 隣接するセルへの相対座標を返す
+    fn offsets(&self) -> Vec<IVec2> {
+        let r = match self {
+            Adjacency::Four | Adjacency::Eight => 1,
+            Adjacency::Manhattan(r) => *r as i32,
+        };
+
+        (-r..=r)
+            .flat_map(|dy| (-r..=r).map(move |dx| ivec2(dx, dy)))
+            .filter(|d| *d != IVec2::ZERO)
+            .filter(|d| match self {
+                Adjacency::Eight => true,
+                _ => d.x.abs() + d.y.abs() <= r,
+            })
+            .collect()
+    }
+}
+
+/// 数値の表記
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum NumberSyntax {
+    Decimal,
+    Hex,
+}
+
+impl NumberSyntax {
+    fn radix(&self) -> u32 {
+        match self {
+            NumberSyntax::Decimal => 10,
+            NumberSyntax::Hex => 16,
+        }
+    }
+}
+
+/// 回路図上の数値。行をまたぐ場合は cells が複数行に渡る。
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Figure {
+    pub value: i64,
+    pub cells: Vec<UVec2>,
+}
+
+/// 回路図上の記号
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Symbol {
+    pub kind: char,
+    pub pos: UVec2,
+}
+
+/// 記号と、それに隣接する数値の組
+#[derive(Debug)]
+pub struct Gear<'s> {
+    pub symbol: &'s Symbol,
+    pub parts: Vec<&'s Figure>,
+}
+
+impl Gear<'_> {
+    /// 隣接する数値すべての積。i64 に収まらなければ None
+    pub fn ratio(&self) -> Option<i64> {
+        self.parts
+            .iter()
+            .try_fold(1i64, |product, n| product.checked_mul(n.value))
+    }
+}
+
+/// 回路図のパーサ。記号の種類、隣接の範囲、数値の表記を設定できる。
+#[derive(Debug, Clone)]
+pub struct SchematicParser {
+    symbols: SymbolClass,
+    adjacency: Adjacency,
+    syntax: NumberSyntax,
+    signed: bool,
+    wrap: bool,
+}
+
+impl Default for SchematicParser {
+    fn default() -> Self {
+        Self::part_numbers()
+    }
+}
+
+impl SchematicParser {
+    /// part1 の設定: '.' 以外の記号に8近傍で隣接する10進数
+    pub fn part_numbers() -> Self {
+        Self {
+            symbols: SymbolClass::Punctuation,
+            adjacency: Adjacency::Eight,
+            syntax: NumberSyntax::Decimal,
+            signed: false,
+            wrap: false,
+        }
+    }
+
+    /// part2 の設定: '*' のみを記号とする
+    pub fn gear_ratios() -> Self {
+        Self::part_numbers().symbols(SymbolClass::Chars(vec!['*']))
+    }
+
+    pub fn symbols(mut self, symbols: SymbolClass) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    pub fn adjacency(mut self, adjacency: Adjacency) -> Self {
+        self.adjacency = adjacency;
+        self
+    }
+
+    pub fn syntax(mut self, syntax: NumberSyntax) -> Self {
+        self.syntax = syntax;
+        self
+    }
+
+    /// 直前の '-' を負の符号として扱う
+    pub fn signed(mut self, signed: bool) -> Self {
+        self.signed = signed;
+        self
+    }
+
+    /// 右端まで続く数値を次の行の先頭に続けて読む
+    pub fn wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn parse(&self, input: &str) -> anyhow::Result<Schematic> {
+        let grid = input
+            .lines()
+            .map(|l| l.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let width = grid.iter().map(|row| row.len()).max().unwrap_or(0);
+        let height = grid.len();
+        let cell = |x: usize, y: usize| grid[y].get(x).copied().unwrap_or('.');
+
+        // 数値を読み取る。owned は数値に使われたセル。
+        let mut figures = vec![];
+        let mut owned = vec![false; width * height];
+        let mut current: Option<(bool, Vec<UVec2>, String)> = None;
+        for y in 0..height {
+            if !self.wrap {
+                figures.extend(self.flush(current.take())?);
+            }
+            for x in 0..width {
+                let c = cell(x, y);
+                if !c.is_digit(self.syntax.radix()) {
+                    figures.extend(self.flush(current.take())?);
+                    continue;
+                }
+
+                let (_, cells, digits) = current.get_or_insert_with(|| {
+                    if self.signed && x > 0 && cell(x - 1, y) == '-' {
+                        (true, vec![uvec2(x as u32 - 1, y as u32)], String::new())
+                    } else {
+                        (false, vec![], String::new())
+                    }
+                });
+                cells.push(uvec2(x as u32, y as u32));
+                digits.push(c);
+            }
+        }
+        figures.extend(self.flush(current.take())?);
+
+        for figure in &figures {
+            for pos in &figure.cells {
+                owned[pos.y as usize * width + pos.x as usize] = true;
+            }
+        }
+
+        // 数値に使われていないセルから記号を読み取る。
+        let mut grid_symbols = vec![None; width * height];
+        let mut symbols = vec![];
+        for y in 0..height {
+            for x in 0..width {
+                let c = cell(x, y);
+                if owned[y * width + x] || !self.symbols.contains(c) {
+                    continue;
+                }
+                grid_symbols[y * width + x] = Some(symbols.len());
+                symbols.push(Symbol {
+                    kind: c,
+                    pos: uvec2(x as u32, y as u32),
+                });
+            }
+        }
+
+        let offsets = self.adjacency.offsets();
+        let mut symbol_numbers = vec![vec![]; symbols.len()];
+        let mut number_symbols = vec![vec![]; figures.len()];
+        for (n, figure) in figures.iter().enumerate() {
+            let mut around = figure
+                .cells
+                .iter()
+                .flat_map(|pos| offsets.iter().map(move |d| pos.as_ivec2() + *d))
+                .filter(|p| p.x >= 0 && p.y >= 0 && p.x < width as i32 && p.y < height as i32)
+                .filter_map(|p| grid_symbols[p.y as usize * width + p.x as usize])
+                .collect::<Vec<_>>();
+            around.sort();
+            around.dedup();
+
+            for &s in &around {
+                symbol_numbers[s].push(n);
+            }
+            number_symbols[n] = around;
+        }
+
+        Ok(Schematic {
+            numbers: figures,
+            symbols,
+            symbol_numbers,
+            number_symbols,
+        })
+    }
+
+    fn flush(&self, current: Option<(bool, Vec<UVec2>, String)>) -> anyhow::Result<Option<Figure>> {
+        let Some((negative, cells, digits)) = current else {
+            return Ok(None);
+        };
+
+        let value = i64::from_str_radix(&digits, self.syntax.radix())
+            .map_err(|e| anyhow::anyhow!("failed to parse {:?} caused by {:?}", digits, e))?;
+        let value = if negative { -value } else { value };
+
+        Ok(Some(Figure { value, cells }))
+    }
+}
+
+/// 記号と数値の隣接関係を一度だけ計算して保持するインデックス
+#[derive(Debug)]
+pub struct Schematic {
+    numbers: Vec<Figure>,
+    symbols: Vec<Symbol>,
+    /// symbols[i] に隣接する numbers の添字
+    symbol_numbers: Vec<Vec<usize>>,
+    /// numbers[i] に隣接する symbols の添字
+    number_symbols: Vec<Vec<usize>>,
+}
+
+impl Schematic {
+    pub fn new(input: &str) -> anyhow::Result<Self> {
+        SchematicParser::default().parse(input)
+    }
+
+    pub fn numbers(&self) -> &[Figure] {
+        &self.numbers
+    }
+
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    /// numbers[index] に隣接する記号を返す
+    pub fn symbols_around(&self, index: usize) -> impl Iterator<Item = &Symbol> {
+        self.number_symbols[index].iter().map(|&s| &self.symbols[s])
+    }
+
+    /// symbols[index] に隣接する数値を返す
+    pub fn numbers_around(&self, index: usize) -> impl Iterator<Item = &Figure> {
+        self.symbol_numbers[index].iter().map(|&n| &self.numbers[n])
+    }
+
+    /// いずれかの記号に隣接している数値（部品番号）を返す
+    pub fn part_numbers(&self) -> impl Iterator<Item = &Figure> {
+        self.numbers
+            .iter()
+            .zip(&self.number_symbols)
+            .filter(|(_, symbols)| !symbols.is_empty())
+            .map(|(number, _)| number)
+    }
+
+    /// 指定した種類の記号に隣接している数値を返す
+    pub fn numbers_adjacent_to(&self, kind: char) -> impl Iterator<Item = &Figure> {
+        self.numbers
+            .iter()
+            .zip(&self.number_symbols)
+            .filter(move |(_, symbols)| symbols.iter().any(|&s| self.symbols[s].kind == kind))
+            .map(|(number, _)| number)
+    }
+
+    /// 指定した種類の記号のうち、ちょうど parts 個の数値に隣接しているものを返す
+    pub fn gears(&self, kind: char, parts: usize) -> impl Iterator<Item = Gear<'_>> {
+        self.symbols
+            .iter()
+            .zip(&self.symbol_numbers)
+            .filter(move |(symbol, numbers)| symbol.kind == kind && numbers.len() == parts)
+            .map(|(symbol, numbers)| Gear {
+                symbol,
+                parts: numbers.iter().map(|&n| &self.numbers[n]).collect(),
+            })
+    }
+}
+
+/// 記号として扱う文字かどうか
+fn is_symbol(c: char) -> bool {
+    c != '.' && c.is_ascii_punctuation()
+}
+
 type Span<'a> = LocatedSpan<&'a str>;
 
-pub fn parse_numbers(input: &str) -> anyhow::Result<Vec<Number>> {
+pub fn parse_numbers(input: &str) -> anyhow::Result<Vec<Number<'_>>> {
     let input = Span::new(input);
     let (_, numbers) = many0(parse_number)(input)
         .map_err(|e| anyhow::anyhow!("failed to parse Numbers caused by {:?}", e))?;
@@ -150,4 +477,170 @@ mod tests {
             assert_eq!(numbers[i].gear_pos(&lines), gear_pos);
         }
     }
+
+    #[test]
+    fn test_schematic_adjacency() {
+        let schematic = Schematic::new(INPUT).unwrap();
+
+        assert_eq!(schematic.symbols().len(), 6);
+        assert_eq!(schematic.part_numbers().count(), 8);
+
+        // 467 は (3,1) の '*' のみに隣接
+        let symbols = schematic.symbols_around(0).collect::<Vec<_>>();
+        assert_eq!(
+            symbols,
+            [&Symbol {
+                kind: '*',
+                pos: uvec2(3, 1)
+            }]
+        );
+
+        // (3,1) の '*' には 467 と 35 が隣接
+        let values = schematic
+            .numbers_around(0)
+            .map(|n| n.value)
+            .collect::<Vec<_>>();
+        assert_eq!(values, [467, 35]);
+
+        let values = schematic
+            .numbers_adjacent_to('#')
+            .map(|n| n.value)
+            .collect::<Vec<_>>();
+        assert_eq!(values, [633]);
+    }
+
+    #[test]
+    fn test_schematic_gears() {
+        let schematic = Schematic::new(INPUT).unwrap();
+
+        let ratios = schematic
+            .gears('*', 2)
+            .map(|g| g.ratio().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ratios, [16345, 451490]);
+
+        let gears = schematic.gears('*', 1).collect::<Vec<_>>();
+        assert_eq!(gears.len(), 1);
+        assert_eq!(gears[0].symbol.pos, uvec2(3, 4));
+        assert_eq!(gears[0].ratio(), Some(617));
+
+        // 数値が多いと積が i64 に収まらない
+        let symbol = Symbol {
+            kind: '*',
+            pos: uvec2(0, 0),
+        };
+        let figure = Figure {
+            value: 999_999,
+            cells: vec![],
+        };
+        let gear = Gear {
+            symbol: &symbol,
+            parts: vec![&figure; 4],
+        };
+        assert_eq!(gear.ratio(), None);
+        let gear = Gear {
+            symbol: &symbol,
+            parts: vec![&figure; 3],
+        };
+        assert_eq!(gear.ratio(), Some(999_999i64.pow(3)));
+    }
+
+    #[test]
+    fn test_schematic_parser_presets() {
+        let schematic = SchematicParser::gear_ratios().parse(INPUT).unwrap();
+        assert_eq!(schematic.symbols().len(), 3);
+        assert_eq!(schematic.part_numbers().count(), 5);
+    }
+
+    #[test]
+    fn test_schematic_parser_adjacency() {
+        let input = indoc! {r#"
+            1...2
+            .*.*.
+            ...3.
+            "#};
+
+        #[rustfmt::skip]
+        let patterns = [
+            (Adjacency::Four,          vec![3]),
+            (Adjacency::Eight,         vec![1, 2, 3]),
+            (Adjacency::Manhattan(2),  vec![1, 2, 3]),
+        ];
+
+        for (adjacency, expected) in patterns {
+            let schematic = SchematicParser::default()
+                .adjacency(adjacency)
+                .parse(input)
+                .unwrap();
+            let values = schematic
+                .part_numbers()
+                .map(|n| n.value)
+                .collect::<Vec<_>>();
+            assert_eq!(values, expected);
+        }
+
+        // 距離2なら '*' を挟んでも届く
+        let schematic = SchematicParser::default()
+            .adjacency(Adjacency::Manhattan(2))
+            .parse("1.*\n")
+            .unwrap();
+        assert_eq!(schematic.part_numbers().count(), 1);
+    }
+
+    #[test]
+    fn test_schematic_parser_syntax() {
+        let input = indoc! {r#"
+            -12..ff
+            ..#..-a
+            "#};
+
+        let schematic = SchematicParser::default()
+            .syntax(NumberSyntax::Hex)
+            .signed(true)
+            .parse(input)
+            .unwrap();
+
+        let values = schematic
+            .numbers()
+            .iter()
+            .map(|n| n.value)
+            .collect::<Vec<_>>();
+        assert_eq!(values, [-0x12, 0xff, -0xa]);
+        // 符号として使われた '-' は記号にならない
+        assert_eq!(schematic.symbols().len(), 1);
+        assert_eq!(schematic.part_numbers().count(), 1);
+    }
+
+    #[test]
+    fn test_schematic_parser_wrap() {
+        let input = indoc! {r#"
+            ...12
+            34...
+            .$...
+            "#};
+
+        let schematic = SchematicParser::default().parse(input).unwrap();
+        let values = schematic
+            .numbers()
+            .iter()
+            .map(|n| n.value)
+            .collect::<Vec<_>>();
+        assert_eq!(values, [12, 34]);
+
+        let schematic = SchematicParser::default().wrap(true).parse(input).unwrap();
+        assert_eq!(
+            schematic.numbers(),
+            [Figure {
+                value: 1234,
+                cells: vec![uvec2(3, 0), uvec2(4, 0), uvec2(0, 1), uvec2(1, 1)],
+            }]
+        );
+        assert_eq!(schematic.part_numbers().count(), 1);
+    }
+
+    #[test]
+    fn test_schematic_parser_invalid_number() {
+        let input = "99999999999999999999\n";
+        assert!(SchematicParser::default().parse(input).is_err());
+    }
 }
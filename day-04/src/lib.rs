@@ -1 +1,300 @@
+use std::io::BufRead;
 
+use nom::bytes::complete::tag;
+use nom::character::complete;
+use nom::character::complete::{space0, space1};
+use nom::multi::separated_list1;
+use nom::sequence::{delimited, tuple};
+use nom::IResult;
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct Card {
+    pub number: u32,
+    pub left: Vec<u32>,
+    pub right: Vec<u32>,
+}
+
+impl Card {
+    /// 当たり番号と点数。点数は u32 に収まらなければ u32::MAX
+    pub fn get_winning(&self) -> (Vec<u32>, u32) {
+        let winning_numbers = self
+            .left
+            .iter()
+            .filter(|n| self.right.contains(n))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if winning_numbers.is_empty() {
+            return (vec![], 0);
+        }
+
+        let point = u32::try_from(winning_numbers.len() - 1)
+            .ok()
+            .and_then(|shift| 1u32.checked_shl(shift))
+            .unwrap_or(u32::MAX);
+        (winning_numbers, point)
+    }
+
+    /// 当たり番号の数
+    pub fn matches(&self) -> usize {
+        self.left.iter().filter(|n| self.right.contains(n)).count()
+    }
+}
+
+/// カードの採点ルール
+pub trait Scoring {
+    fn score(&self, cards: &[Card]) -> u64;
+}
+
+/// part1: 当たり1つ目で1点、以降は当たるごとに倍。
+/// u64 に収まらない点数は、u64::MAX で頭打ちにする。
+pub struct DoublingPoints;
+
+impl Scoring for DoublingPoints {
+    fn score(&self, cards: &[Card]) -> u64 {
+        cards
+            .iter()
+            .map(|card| match card.matches() {
+                0 => 0,
+                n => u32::try_from(n - 1)
+                    .ok()
+                    .and_then(|shift| 1u64.checked_shl(shift))
+                    .unwrap_or(u64::MAX),
+            })
+            .fold(0, |sum, point| sum.saturating_add(point))
+    }
+}
+
+/// part2: 当たり数だけ後続のカードのコピーを得る
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CopyCascade {
+    /// 後続何枚までコピーを得るかの上限
+    pub cap: Option<usize>,
+    /// 1枚あたりに得るコピーの枚数
+    pub multiplier: u64,
+}
+
+impl Default for CopyCascade {
+    fn default() -> Self {
+        Self {
+            cap: None,
+            multiplier: 1,
+        }
+    }
+}
+
+impl Scoring for CopyCascade {
+    fn score(&self, cards: &[Card]) -> u64 {
+        self.cascade(cards).total()
+    }
+}
+
+impl CopyCascade {
+    /// 各カードの枚数を求める。
+    /// コピーを得る範囲を差分配列で足し込むので、カード枚数に対して線形。
+    /// multiplier が大きく u64 に収まらない枚数は、u64::MAX で頭打ちにする。
+    pub fn cascade(&self, cards: &[Card]) -> Cascade {
+        let len = cards.len();
+        let ranges = cards
+            .iter()
+            .enumerate()
+            .map(|(i, card)| {
+                let range = self.cap.map_or(card.matches(), |c| card.matches().min(c));
+                // 最後のカードより先のコピーは得られない
+                range.min(len - i - 1)
+            })
+            .collect::<Vec<_>>();
+
+        let mut counts = vec![0; len];
+        let mut diff = vec![0i128; len + 1];
+        let mut won = 0i128;
+        for i in 0..len {
+            won += diff[i];
+            counts[i] = u64::try_from(won).unwrap_or(u64::MAX).saturating_add(1);
+
+            let copies = counts[i].saturating_mul(self.multiplier) as i128;
+            diff[i + 1] += copies;
+            diff[i + 1 + ranges[i]] -= copies;
+        }
+
+        Cascade {
+            counts,
+            ranges,
+            multiplier: self.multiplier,
+        }
+    }
+}
+
+/// コピーの連鎖を計算した結果
+#[derive(Debug, Eq, PartialEq)]
+pub struct Cascade {
+    /// 各カードの最終的な枚数（元の1枚を含む）
+    pub counts: Vec<u64>,
+    /// 各カードがコピーを得る後続カードの枚数
+    pub ranges: Vec<usize>,
+    multiplier: u64,
+}
+
+impl Cascade {
+    /// 枚数の合計。u64 に収まらなければ u64::MAX
+    pub fn total(&self) -> u64 {
+        self.counts.iter().fold(0, |sum, &c| sum.saturating_add(c))
+    }
+
+    /// cards[index] のコピーを生んだカードの添字と、そこから得た枚数を返す
+    pub fn sources(&self, index: usize) -> Vec<(usize, u64)> {
+        (0..index)
+            .filter(|&j| j + self.ranges[j] >= index)
+            .map(|j| (j, self.counts[j].saturating_mul(self.multiplier)))
+            .collect()
+    }
+}
+
+pub fn parse_card(input: &str) -> IResult<&str, Card> {
+    let (input, number) = delimited(
+        tuple((tag("Card"), space0)),
+        complete::u32,
+        tuple((tag(":"), space0)),
+    )(input)?;
+    let (input, left) = separated_list1(space1, complete::u32)(input)?;
+    let (input, _) = tuple((space0, tag("|"), space0))(input)?;
+    let (input, right) = separated_list1(space1, complete::u32)(input)?;
+
+    Ok((
+        input,
+        Card {
+            number,
+            left,
+            right,
+        },
+    ))
+}
+
+pub fn parse_cards(r: &mut impl BufRead) -> anyhow::Result<Vec<Card>> {
+    let mut cards = vec![];
+    for line in r.lines() {
+        let line = line?;
+        let (_, card) = parse_card(&line).map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        cards.push(card);
+    }
+    Ok(cards)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    const INPUT: &str = indoc! {r#"
+    Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+    Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+    Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+    Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+    Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+    Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11
+    "#};
+
+    fn cards() -> Vec<Card> {
+        parse_cards(&mut INPUT.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_card() {
+        let lines = INPUT.lines().collect::<Vec<_>>();
+
+        let (_, card) = parse_card(lines[0]).unwrap();
+        assert_eq!(
+            card,
+            Card {
+                number: 1,
+                left: vec![41, 48, 83, 86, 17],
+                right: vec![83, 86, 6, 31, 17, 9, 48, 53],
+            }
+        );
+    }
+
+    #[test]
+    fn test_get_winning() {
+        let expects = [
+            (vec![48, 83, 86, 17], 8),
+            (vec![32, 61], 2),
+            (vec![1, 21], 2),
+            (vec![84], 1),
+            (vec![], 0),
+            (vec![], 0),
+        ];
+
+        for (i, card) in cards().iter().enumerate() {
+            assert_eq!(card.get_winning(), expects[i]);
+            assert_eq!(card.matches(), expects[i].0.len());
+        }
+    }
+
+    #[test]
+    fn test_scoring() {
+        let cards = cards();
+        assert_eq!(DoublingPoints.score(&cards), 13);
+        assert_eq!(CopyCascade::default().score(&cards), 30);
+
+        // 当たりが多いと点数が頭打ちになる
+        let card = |matches: u32| Card {
+            number: 1,
+            left: (1..=matches).collect(),
+            right: (1..=matches).collect(),
+        };
+        assert_eq!(DoublingPoints.score(&[card(33)]), 1 << 32);
+        assert_eq!(DoublingPoints.score(&[card(64)]), 1 << 63);
+        assert_eq!(DoublingPoints.score(&[card(65)]), u64::MAX);
+        assert_eq!(DoublingPoints.score(&[card(64), card(64)]), u64::MAX);
+        assert_eq!(card(33).get_winning().1, u32::MAX);
+    }
+
+    #[test]
+    fn test_cascade() {
+        let cascade = CopyCascade::default().cascade(&cards());
+        assert_eq!(cascade.counts, [1, 2, 4, 8, 14, 1]);
+
+        // Card 4 は Card 1, 2, 3 からコピーを得る
+        assert_eq!(cascade.sources(3), [(0, 1), (1, 2), (2, 4)]);
+        assert_eq!(cascade.sources(0), []);
+        assert_eq!(cascade.sources(5), []);
+    }
+
+    #[test]
+    fn test_cascade_rules() {
+        let cards = cards();
+
+        let capped = CopyCascade {
+            cap: Some(1),
+            multiplier: 1,
+        };
+        assert_eq!(capped.cascade(&cards).counts, [1, 2, 3, 4, 5, 1]);
+
+        let doubled = CopyCascade {
+            cap: None,
+            multiplier: 2,
+        };
+        let cascade = doubled.cascade(&cards);
+        assert_eq!(cascade.counts, [1, 3, 9, 27, 75, 1]);
+        assert_eq!(cascade.sources(2), [(0, 2), (1, 6)]);
+
+        // 枚数が u64 に収まらなければ頭打ちになる
+        let huge = CopyCascade {
+            cap: None,
+            multiplier: u64::MAX,
+        };
+        let cascade = huge.cascade(&cards);
+        assert_eq!(cascade.counts[..3], [1, u64::MAX, u64::MAX]);
+        assert_eq!(cascade.sources(2), [(0, u64::MAX), (1, u64::MAX)]);
+        assert_eq!(huge.score(&cards), u64::MAX);
+    }
+
+    #[test]
+    fn test_pow() {
+        assert_eq!(2u32.pow(0), 1);
+        assert_eq!(2u32.pow(1), 2);
+        assert_eq!(2u32.pow(2), 4);
+        assert_eq!(2u32.pow(3), 8);
+    }
+}
@@ -1 +1,710 @@
+use std::fmt;
+use std::ops::Range;
 
+use glam::I64Vec3;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete;
+use nom::character::complete::{newline, space0, space1};
+use nom::multi::separated_list1;
+use nom::sequence::tuple;
+use nom::IResult;
+
+#[derive(Debug)]
+pub struct Map<'a> {
+    pub label: &'a str,
+    pub list: Vec<I64Vec3>,
+}
+
+impl Map<'_> {
+    pub fn convert(&self, src: i64) -> i64 {
+        for data in &self.list {
+            if let Some(value) = convert(data, src) {
+                return value;
+            }
+        }
+        src
+    }
+
+    pub fn to_interval_map(&self) -> anyhow::Result<IntervalMap> {
+        IntervalMap::new(self.list.iter().map(|data| Piece {
+            start: data.y,
+            end: data.y + data.z,
+            offset: data.x - data.y,
+        }))
+        .map_err(|e| anyhow::anyhow!("{}: {}", self.label, e))
+    }
+
+    /// 変換元と変換先のカテゴリ名
+    pub fn categories(&self) -> (&str, &str) {
+        self.label
+            .split_once("-to-")
+            .unwrap_or((self.label, self.label))
+    }
+
+    /// 値に適用される行の添字。どの行にも当てはまらなければ None
+    pub fn find_line(&self, src: i64) -> Option<usize> {
+        self.list
+            .iter()
+            .position(|data| convert(data, src).is_some())
+    }
+
+    fn offset(&self, line: Option<usize>) -> i64 {
+        line.map_or(0, |i| self.list[i].x - self.list[i].y)
+    }
+
+    /// 全域を、適用される行ごとに区切って返す
+    fn segments(&self) -> Vec<(Range<i64>, Option<usize>)> {
+        let mut lines = self
+            .list
+            .iter()
+            .enumerate()
+            .map(|(i, data)| (data.y..data.y + data.z, i))
+            .collect::<Vec<_>>();
+        lines.sort_by_key(|(r, _)| r.start);
+
+        let mut results = Vec::new();
+        let mut cursor = i64::MIN;
+        for (r, i) in lines {
+            if r.end <= cursor {
+                continue;
+            }
+            if cursor < r.start {
+                results.push((cursor..r.start, None));
+            }
+            results.push((cursor.max(r.start)..r.end, Some(i)));
+            cursor = r.end;
+        }
+        if cursor < i64::MAX {
+            results.push((cursor..i64::MAX, None));
+        }
+
+        results
+    }
+
+    /// range を、適用される行ごとに分割して返す
+    pub fn split_range(&self, range: Range<i64>) -> Vec<(Range<i64>, Option<usize>)> {
+        self.segments()
+            .into_iter()
+            .filter_map(|(seg, line)| {
+                let start = seg.start.max(range.start);
+                let end = seg.end.min(range.end);
+                (start < end).then_some((start..end, line))
+            })
+            .collect()
+    }
+
+    /// 変換後に range に入る変換元の範囲を、適用される行ごとに返す
+    pub fn reverse_range(&self, range: Range<i64>) -> Vec<(Range<i64>, Option<usize>)> {
+        self.segments()
+            .into_iter()
+            .filter_map(|(seg, line)| {
+                let offset = self.offset(line);
+                let start = seg.start.max(range.start - offset);
+                let end = seg.end.min(range.end - offset);
+                (start < end).then_some((start..end, line))
+            })
+            .collect()
+    }
+}
+
+/// 変換の1段階
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Step<'a> {
+    pub label: &'a str,
+    pub src: Range<i64>,
+    pub dst: Range<i64>,
+    /// 適用された行の添字。どの行にも当てはまらず素通りした場合は None
+    pub line: Option<usize>,
+}
+
+/// seed から location までの変換の経路。
+/// 各段階は値を一様にずらすだけなので、どの段階でも範囲の長さは同じ。
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Trace<'a> {
+    pub steps: Vec<Step<'a>>,
+}
+
+impl<'a> Trace<'a> {
+    /// 各段階で適用された行から経路を組み立てる
+    fn build(maps: &[Map<'a>], seeds: Range<i64>, lines: &[Option<usize>]) -> Self {
+        let mut steps = Vec::with_capacity(maps.len());
+        let mut src = seeds;
+        for (map, &line) in maps.iter().zip(lines) {
+            let offset = map.offset(line);
+            let dst = src.start + offset..src.end + offset;
+            steps.push(Step {
+                label: map.label,
+                src,
+                dst: dst.clone(),
+                line,
+            });
+            src = dst;
+        }
+        Self { steps }
+    }
+
+    pub fn seeds(&self) -> Range<i64> {
+        self.steps.first().map_or(0..0, |s| s.src.clone())
+    }
+
+    pub fn locations(&self) -> Range<i64> {
+        self.steps.last().map_or(0..0, |s| s.dst.clone())
+    }
+
+    /// seed の範囲を seeds に絞り込んだ経路を返す。重ならなければ None
+    pub fn restrict(&self, seeds: Range<i64>) -> Option<Self> {
+        let current = self.seeds();
+        let start = current.start.max(seeds.start);
+        let end = current.end.min(seeds.end);
+        if start >= end {
+            return None;
+        }
+
+        let shift = start - current.start;
+        let len = end - start;
+        let narrow = |r: &Range<i64>| r.start + shift..r.start + shift + len;
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| Step {
+                src: narrow(&step.src),
+                dst: narrow(&step.dst),
+                ..step.clone()
+            })
+            .collect();
+        Some(Self { steps })
+    }
+}
+
+impl fmt::Display for Trace<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "seeds {:?} => locations {:?}",
+            self.seeds(),
+            self.locations()
+        )?;
+        for step in &self.steps {
+            write!(f, "\n  {}: {:?} -> {:?}", step.label, step.src, step.dst)?;
+            match step.line {
+                Some(i) => write!(f, " (line {})", i + 1)?,
+                None => write!(f, " (not mapped)")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// seed の範囲から location までの経路を返す
+pub fn trace<'a>(maps: &[Map<'a>], seeds: Range<i64>) -> Vec<Trace<'a>> {
+    // (seed の範囲, 現在の段階での範囲, 適用された行)
+    let mut chains = vec![(seeds.clone(), seeds, vec![])];
+    for map in maps {
+        let mut next = Vec::new();
+        for (seeds, current, lines) in chains {
+            for (sub, line) in map.split_range(current.clone()) {
+                let shift = sub.start - current.start;
+                let len = sub.end - sub.start;
+                let offset = map.offset(line);
+
+                let mut lines = lines.clone();
+                lines.push(line);
+                next.push((
+                    seeds.start + shift..seeds.start + shift + len,
+                    sub.start + offset..sub.end + offset,
+                    lines,
+                ));
+            }
+        }
+        chains = next;
+    }
+
+    chains
+        .into_iter()
+        .map(|(seeds, _, lines)| Trace::build(maps, seeds, &lines))
+        .collect()
+}
+
+/// location の範囲に行き着く seed とその経路を返す
+pub fn provenance<'a>(maps: &[Map<'a>], locations: Range<i64>) -> Vec<Trace<'a>> {
+    // (現在の段階での範囲, 後ろの段階から順に適用された行)
+    let mut chains = vec![(locations, vec![])];
+    for map in maps.iter().rev() {
+        let mut next = Vec::new();
+        for (current, lines) in chains {
+            for (sub, line) in map.reverse_range(current) {
+                let mut lines = lines.clone();
+                lines.push(line);
+                next.push((sub, lines));
+            }
+        }
+        chains = next;
+    }
+
+    let mut traces = chains
+        .into_iter()
+        .map(|(seeds, mut lines)| {
+            lines.reverse();
+            Trace::build(maps, seeds, &lines)
+        })
+        .collect::<Vec<_>>();
+    traces.sort_by_key(|t| t.seeds().start);
+    traces
+}
+
+pub fn convert(data: &I64Vec3, src: i64) -> Option<i64> {
+    if (data.y..(data.y + data.z)).contains(&src) {
+        let diff = data.x - data.y;
+        return Some(src + diff);
+    }
+    None
+}
+
+/// すべての変換表を順に合成した一つの写像を返す
+pub fn compose_maps(maps: &[Map]) -> anyhow::Result<IntervalMap> {
+    let mut composed = IntervalMap::default();
+    for map in maps {
+        composed = composed.compose(&map.to_interval_map()?);
+    }
+    Ok(composed)
+}
+
+/// [start, end) の値に offset を足す区間
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Piece {
+    pub start: i64,
+    pub end: i64,
+    pub offset: i64,
+}
+
+/// 区分ごとに offset を足す写像。どの区間にも含まれない値はそのまま。
+/// 区間は start でソートされ、重なりを持たない。
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct IntervalMap {
+    pieces: Vec<Piece>,
+}
+
+impl IntervalMap {
+    pub fn new(pieces: impl IntoIterator<Item = Piece>) -> anyhow::Result<Self> {
+        let mut pieces = pieces
+            .into_iter()
+            .filter(|p| p.start < p.end)
+            .collect::<Vec<_>>();
+        pieces.sort_by_key(|p| p.start);
+
+        for (a, b) in pieces.iter().zip(pieces.iter().skip(1)) {
+            if b.start < a.end {
+                anyhow::bail!("overlapping pieces {:?} and {:?}", a, b);
+            }
+        }
+
+        Ok(Self { pieces })
+    }
+
+    pub fn pieces(&self) -> &[Piece] {
+        &self.pieces
+    }
+
+    pub fn get(&self, src: i64) -> i64 {
+        let i = self.pieces.partition_point(|p| p.end <= src);
+        match self.pieces.get(i) {
+            Some(p) if p.start <= src => src + p.offset,
+            _ => src,
+        }
+    }
+
+    /// range を適用される offset ごとに分割して返す
+    fn split(&self, range: Range<i64>) -> Vec<(Range<i64>, i64)> {
+        let mut results = Vec::new();
+        let mut cursor = range.start;
+
+        let first = self.pieces.partition_point(|p| p.end <= range.start);
+        for p in self.pieces[first..]
+            .iter()
+            .take_while(|p| p.start < range.end)
+        {
+            if cursor < p.start {
+                results.push((cursor..p.start, 0));
+            }
+            let start = cursor.max(p.start);
+            let end = range.end.min(p.end);
+            results.push((start..end, p.offset));
+            cursor = end;
+        }
+        if cursor < range.end {
+            results.push((cursor..range.end, 0));
+        }
+
+        results
+    }
+
+    /// range の像を区間のリストで返す
+    pub fn apply_range(&self, range: Range<i64>) -> Vec<Range<i64>> {
+        self.split(range)
+            .into_iter()
+            .map(|(r, offset)| r.start + offset..r.end + offset)
+            .collect()
+    }
+
+    /// self を適用した後に then を適用する写像を返す
+    pub fn compose(&self, then: &IntervalMap) -> IntervalMap {
+        let mut pieces = Vec::new();
+        for (r, offset) in self.split(i64::MIN..i64::MAX) {
+            for (image, then_offset) in then.split(r.start + offset..r.end + offset) {
+                pieces.push(Piece {
+                    start: image.start - offset,
+                    end: image.end - offset,
+                    offset: offset + then_offset,
+                });
+            }
+        }
+
+        IntervalMap { pieces }.simplify()
+    }
+
+    /// 写像した結果が range に入る値の範囲を返す
+    pub fn preimage(&self, range: Range<i64>) -> Vec<Range<i64>> {
+        let mut results = self
+            .split(i64::MIN..i64::MAX)
+            .into_iter()
+            .filter_map(|(r, offset)| {
+                let start = r.start.max(range.start - offset);
+                let end = r.end.min(range.end - offset);
+                (start < end).then_some(start..end)
+            })
+            .collect::<Vec<_>>();
+        results.sort_by_key(|r| r.start);
+        results
+    }
+
+    /// 逆写像を返す。全単射でない場合はエラー。
+    pub fn invert(&self) -> anyhow::Result<IntervalMap> {
+        let mut images = self
+            .split(i64::MIN..i64::MAX)
+            .into_iter()
+            .map(|(r, offset)| r.start + offset..r.end + offset)
+            .collect::<Vec<_>>();
+        images.sort_by_key(|r| r.start);
+
+        let mut cursor = i64::MIN;
+        for image in &images {
+            if image.start != cursor {
+                anyhow::bail!("not invertible around {}", cursor.max(image.start));
+            }
+            cursor = image.end;
+        }
+
+        IntervalMap::new(self.pieces.iter().map(|p| Piece {
+            start: p.start + p.offset,
+            end: p.end + p.offset,
+            offset: -p.offset,
+        }))
+    }
+
+    /// offset がゼロの区間を取り除き、隣接する同じ offset の区間をまとめる
+    pub fn simplify(self) -> IntervalMap {
+        let mut pieces: Vec<Piece> = Vec::with_capacity(self.pieces.len());
+        for p in self.pieces {
+            if p.offset == 0 || p.start >= p.end {
+                continue;
+            }
+            match pieces.last_mut() {
+                Some(last) if last.end == p.start && last.offset == p.offset => last.end = p.end,
+                _ => pieces.push(p),
+            }
+        }
+        IntervalMap { pieces }
+    }
+}
+
+fn parse_map_line(input: &str) -> IResult<&str, I64Vec3> {
+    let (input, numbers) = separated_list1(space1, complete::i64)(input)?;
+    if numbers.len() != 3 {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::TooLarge,
+        )));
+    }
+    Ok((input, I64Vec3::from_slice(&numbers)))
+}
+
+fn parse_map(input: &str) -> IResult<&str, Map<'_>> {
+    let (input, label) = take_while1(|c: char| c.is_alphabetic() || c == '-')(input)?;
+    let (input, _) = tag(" map:")(input)?;
+    let (input, _) = newline(input)?;
+    let (input, list) = separated_list1(newline, parse_map_line)(input)?;
+    Ok((input, Map { label, list }))
+}
+
+pub fn parse_data(input: &str) -> IResult<&str, (Vec<i64>, Vec<Map<'_>>)> {
+    let (input, _) = tag("seeds:")(input)?;
+    let (input, _) = space0(input)?;
+    let (input, seeds) = separated_list1(space1, complete::i64)(input)?;
+    let (input, _) = tuple((newline, newline))(input)?;
+    let (input, maps) = separated_list1(tuple((newline, newline)), parse_map)(input)?;
+
+    Ok((input, (seeds, maps)))
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::i64vec3;
+    use indoc::indoc;
+
+    use super::*;
+
+    const INPUT: &str = indoc! {r#"
+    seeds: 79 14 55 13
+
+    seed-to-soil map:
+    50 98 2
+    52 50 48
+
+    soil-to-fertilizer map:
+    0 15 37
+    37 52 2
+    39 0 15
+
+    fertilizer-to-water map:
+    49 53 8
+    0 11 42
+    42 0 7
+    57 7 4
+
+    water-to-light map:
+    88 18 7
+    18 25 70
+
+    light-to-temperature map:
+    45 77 23
+    81 45 19
+    68 64 13
+
+    temperature-to-humidity map:
+    0 69 1
+    1 0 69
+
+    humidity-to-location map:
+    60 56 37
+    56 93 4
+    "#};
+
+    #[test]
+    fn test_parse_map() {
+        let (_, data) = parse_data(INPUT).unwrap();
+        println!("{:#?}", data);
+    }
+
+    #[test]
+    fn test_map_convert() {
+        let (_, (_, data)) = parse_data(INPUT).unwrap();
+
+        let converter = &data[0];
+        let interval_map = converter.to_interval_map().unwrap();
+        assert_eq!(converter.label, "seed-to-soil");
+
+        #[rustfmt::skip]
+        let patterns = [
+            (0, 0), (1, 1), (48, 48), (49, 49), (50, 52),
+            (51, 53), (96, 98), (97, 99), (98, 50), (99, 51),
+        ];
+        for (src, expected) in patterns {
+            assert_eq!(converter.convert(src), expected);
+            assert_eq!(interval_map.get(src), expected);
+        }
+    }
+
+    #[test]
+    fn test_convert() {
+        let c = i64vec3(50, 98, 2);
+        assert_eq!(convert(&c, 98), Some(50));
+        assert_eq!(convert(&c, 99), Some(51));
+        assert_eq!(convert(&c, 10), None);
+
+        let c = i64vec3(52, 50, 48);
+        assert_eq!(convert(&c, 53), Some(55));
+        assert_eq!(convert(&c, 10), None);
+    }
+
+    #[test]
+    fn test_apply_range() {
+        let map = IntervalMap::new([Piece {
+            start: 100,
+            end: 110,
+            offset: 100,
+        }])
+        .unwrap();
+
+        assert_eq!(map.apply_range(99..100), vec![99..100]);
+        assert_eq!(map.apply_range(99..104), vec![99..100, 200..204]);
+        assert_eq!(map.apply_range(105..108), vec![205..208]);
+        assert_eq!(map.apply_range(105..115), vec![205..210, 110..115]);
+        assert_eq!(map.apply_range(110..120), vec![110..120]);
+        assert_eq!(map.apply_range(95..115), vec![95..100, 200..210, 110..115]);
+    }
+
+    #[test]
+    fn test_new_overlapping() {
+        let pieces = [
+            Piece {
+                start: 0,
+                end: 10,
+                offset: 1,
+            },
+            Piece {
+                start: 5,
+                end: 15,
+                offset: 2,
+            },
+        ];
+        assert!(IntervalMap::new(pieces).is_err());
+    }
+
+    #[test]
+    fn test_compose() {
+        let (_, (seeds, maps)) = parse_data(INPUT).unwrap();
+        let composed = compose_maps(&maps).unwrap();
+
+        for seed in 0..120 {
+            let expected = maps.iter().fold(seed, |v, map| map.convert(v));
+            assert_eq!(composed.get(seed), expected);
+        }
+
+        let locations = seeds.iter().map(|&s| composed.get(s)).collect::<Vec<_>>();
+        assert_eq!(locations, [82, 43, 86, 35]);
+    }
+
+    #[test]
+    fn test_invert() {
+        let (_, (_, maps)) = parse_data(INPUT).unwrap();
+        let composed = compose_maps(&maps).unwrap();
+        let inverted = composed.invert().unwrap();
+
+        for seed in 0..120 {
+            assert_eq!(inverted.get(composed.get(seed)), seed);
+        }
+        assert_eq!(composed.compose(&inverted), IntervalMap::default());
+
+        // 二つの区間が同じ値に写るので逆写像を持たない
+        let map = IntervalMap::new([Piece {
+            start: 0,
+            end: 10,
+            offset: 10,
+        }])
+        .unwrap();
+        assert!(map.invert().is_err());
+    }
+
+    #[test]
+    fn test_simplify() {
+        let map = IntervalMap {
+            pieces: vec![
+                Piece {
+                    start: 0,
+                    end: 5,
+                    offset: 3,
+                },
+                Piece {
+                    start: 5,
+                    end: 8,
+                    offset: 3,
+                },
+                Piece {
+                    start: 8,
+                    end: 9,
+                    offset: 0,
+                },
+            ],
+        };
+
+        assert_eq!(
+            map.simplify().pieces(),
+            [Piece {
+                start: 0,
+                end: 8,
+                offset: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_find_line() {
+        let (_, (_, maps)) = parse_data(INPUT).unwrap();
+
+        assert_eq!(maps[0].categories(), ("seed", "soil"));
+        assert_eq!(maps[0].find_line(98), Some(0));
+        assert_eq!(maps[0].find_line(79), Some(1));
+        assert_eq!(maps[0].find_line(10), None);
+    }
+
+    #[test]
+    fn test_trace() {
+        let (_, (_, maps)) = parse_data(INPUT).unwrap();
+
+        let traces = trace(&maps, 79..80);
+        assert_eq!(traces.len(), 1);
+
+        // seed 79 -> soil 81 -> fertilizer 81 -> water 81 -> light 74 -> temperature 78 -> humidity 78 -> location 82
+        let values = traces[0]
+            .steps
+            .iter()
+            .map(|s| s.dst.start)
+            .collect::<Vec<_>>();
+        assert_eq!(values, [81, 81, 81, 74, 78, 78, 82]);
+        let lines = traces[0].steps.iter().map(|s| s.line).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [Some(1), None, None, Some(1), Some(2), None, Some(0)]
+        );
+
+        // 範囲を渡すと、適用される行が変わる位置で分かれる
+        let traces = trace(&maps, 79..93);
+        let total = traces
+            .iter()
+            .map(|t| t.seeds().end - t.seeds().start)
+            .sum::<i64>();
+        assert_eq!(total, 14);
+        for t in &traces {
+            for seed in t.seeds() {
+                let expected = maps.iter().fold(seed, |v, map| map.convert(v));
+                assert_eq!(t.locations().start + (seed - t.seeds().start), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_provenance() {
+        let (_, (_, maps)) = parse_data(INPUT).unwrap();
+        let composed = compose_maps(&maps).unwrap();
+
+        let traces = provenance(&maps, 46..47);
+        let seeds = traces.iter().map(|t| t.seeds()).collect::<Vec<_>>();
+        assert_eq!(seeds, composed.preimage(46..47));
+        assert!(seeds.contains(&(82..83)));
+
+        let trace = traces.iter().find(|t| t.seeds() == (82..83)).unwrap();
+        let values = trace.steps.iter().map(|s| s.dst.start).collect::<Vec<_>>();
+        assert_eq!(values, [84, 84, 84, 77, 45, 46, 46]);
+
+        // seed の範囲で絞り込む
+        let restricted = traces
+            .iter()
+            .filter_map(|t| t.restrict(79..93))
+            .collect::<Vec<_>>();
+        assert_eq!(restricted.len(), 1);
+        assert_eq!(restricted[0].locations(), 46..47);
+    }
+
+    #[test]
+    fn test_preimage() {
+        let map = IntervalMap::new([Piece {
+            start: 100,
+            end: 110,
+            offset: 100,
+        }])
+        .unwrap();
+
+        assert_eq!(map.preimage(95..105), vec![95..100]);
+        assert_eq!(map.preimage(95..205), vec![95..100, 100..105, 110..205]);
+    }
+}
@@ -1,7 +1,7 @@
 use std::fs;
 use std::io::{BufReader, Read};
 
-use day_06::{merge_records, parse_input};
+use day_06::{parse_input, Race};
 
 fn main() -> anyhow::Result<()> {
     let mut r = BufReader::new(fs::File::open("day-06/data/input.txt")?);
@@ -15,8 +15,8 @@ fn main() -> anyhow::Result<()> {
 
 fn part2(input: &str) -> anyhow::Result<String> {
     let (_, records) = parse_input(input).map_err(|e| anyhow::anyhow!("{:?}", e))?;
-    let record = merge_records(&records);
-    let value = record.count_win_ways();
+    let races = records.iter().map(Race::from).collect::<Vec<_>>();
+    let value = Race::merge(&races)?.count_win_ways();
     Ok(value.to_string())
 }
 
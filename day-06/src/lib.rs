@@ -1,3 +1,5 @@
+use std::ops::RangeInclusive;
+
 use itertools::Itertools;
 use nom::bytes::complete::tag;
 use nom::character::complete;
@@ -14,6 +16,11 @@ pub struct Record {
 
 impl Record {
     pub fn count_win_ways(&self) -> usize {
+        count_win_ways(self.time.max(0) as u64, self.distance.max(0) as u128) as usize
+    }
+
+    /// すべての押下時間を試して数える
+    pub fn count_win_ways_naive(&self) -> usize {
         (1..self.time)
             .map(|press_time| calc_distance(press_time, self.time))
             .filter(|&distance| distance > self.distance)
@@ -25,6 +32,193 @@ fn calc_distance(press_time: i64, time: i64) -> i64 {
     press_time * (time - press_time)
 }
 
+/// press * (time - press) > distance となる press の数を返す。
+/// press^2 - time * press + distance < 0 の2つの解の間にある整数を数える。
+pub fn count_win_ways(time: u64, distance: u128) -> u64 {
+    let time = time as u128;
+    let wins = |press: u128| press * (time - press) > distance;
+
+    // 判別式が負なら一度も距離を超えない
+    let Some(discriminant) = distance
+        .checked_mul(4)
+        .and_then(|d| (time * time).checked_sub(d))
+    else {
+        return 0;
+    };
+
+    // 小さい方の解の近くから、境界（同着は負け）を整数で確かめる
+    let mut low = (time - isqrt(discriminant)) / 2;
+    while low > 0 && wins(low - 1) {
+        low -= 1;
+    }
+    while low <= time / 2 && !wins(low) {
+        low += 1;
+    }
+    if low > time / 2 {
+        return 0;
+    }
+
+    // 距離は time / 2 を軸に対称
+    (time - 2 * low + 1) as u64
+}
+
+/// 整数の平方根（切り捨て）
+pub fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+
+    // sqrt(n) 以上の値から始めるニュートン法
+    let mut x = 1u128 << (128 - n.leading_zeros()).div_ceil(2);
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// ボタンを押した時間から船の速度を決める
+pub trait Charge {
+    fn speed(&self, press: u64) -> u128;
+}
+
+/// 押した時間に比例して速くなる
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Linear {
+    pub acceleration: u64,
+}
+
+impl Default for Linear {
+    fn default() -> Self {
+        Self { acceleration: 1 }
+    }
+}
+
+impl Charge for Linear {
+    fn speed(&self, press: u64) -> u128 {
+        press as u128 * self.acceleration as u128
+    }
+}
+
+/// 押した時間に比例して速くなるが、max_speed で頭打ちになる
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Capped {
+    pub acceleration: u64,
+    pub max_speed: u64,
+}
+
+impl Charge for Capped {
+    fn speed(&self, press: u64) -> u128 {
+        (press as u128 * self.acceleration as u128).min(self.max_speed as u128)
+    }
+}
+
+/// レースの条件。速度は押した時間に対して単調増加で、
+/// 進む距離は押した時間に対して単峰であることを前提にする。
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Race<C = Linear> {
+    pub time: u64,
+    pub distance: u128,
+    pub charge: C,
+}
+
+impl From<&Record> for Race {
+    fn from(record: &Record) -> Self {
+        Self {
+            time: record.time.max(0) as u64,
+            distance: record.distance.max(0) as u128,
+            charge: Linear::default(),
+        }
+    }
+}
+
+impl<C: Charge> Race<C> {
+    /// press だけ押したときに進む距離。time 以上押すと進めない
+    pub fn travel(&self, press: u64) -> u128 {
+        let Some(rest) = self.time.checked_sub(press) else {
+            return 0;
+        };
+        self.charge.speed(press).saturating_mul(rest as u128)
+    }
+
+    /// 最も遠くまで進む押下時間（複数あれば小さい方）
+    pub fn optimal_press(&self) -> u64 {
+        partition_point(0, self.time, |p| self.travel(p + 1) > self.travel(p))
+    }
+
+    pub fn best_distance(&self) -> u128 {
+        self.travel(self.optimal_press())
+    }
+
+    /// 最適に押したときに記録をどれだけ上回るか
+    pub fn margin(&self) -> i128 {
+        self.best_distance() as i128 - self.distance as i128
+    }
+
+    /// 記録を超える押下時間の範囲
+    pub fn winning_interval(&self) -> Option<RangeInclusive<u64>> {
+        let optimal = self.optimal_press();
+        if self.travel(optimal) <= self.distance {
+            return None;
+        }
+
+        let low = partition_point(0, optimal, |p| self.travel(p) <= self.distance);
+        let high = partition_point(optimal, self.time, |p| self.travel(p) > self.distance);
+        Some(low..=high - 1)
+    }
+
+    pub fn count_win_ways(&self) -> u64 {
+        self.winning_interval()
+            .map_or(0, |r| r.end() - r.start() + 1)
+    }
+}
+
+impl<C: Clone> Race<C> {
+    /// 各レースの数字を続けて書いた一つのレースにする。速度のモデルは最初のレースのもの。
+    pub fn merge(races: &[Race<C>]) -> anyhow::Result<Self> {
+        let first = races.first().ok_or(anyhow::anyhow!("no races"))?;
+
+        let mut time = 0u128;
+        let mut distance = 0u128;
+        for race in races {
+            time =
+                concat_digits(time, race.time as u128).ok_or(anyhow::anyhow!("time overflow"))?;
+            distance = concat_digits(distance, race.distance)
+                .ok_or(anyhow::anyhow!("distance overflow"))?;
+        }
+
+        Ok(Self {
+            time: u64::try_from(time)?,
+            distance,
+            charge: first.charge.clone(),
+        })
+    }
+}
+
+/// [lo, hi) のうち、pred を満たさなくなる最初の位置
+fn partition_point(mut lo: u64, mut hi: u64, pred: impl Fn(u64) -> bool) -> u64 {
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if pred(mid) {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    lo
+}
+
+/// a の後ろに b の桁を続けた数
+fn concat_digits(a: u128, b: u128) -> Option<u128> {
+    let mut shift = 10u128;
+    while shift <= b {
+        shift = shift.checked_mul(10)?;
+    }
+    a.checked_mul(shift)?.checked_add(b)
+}
+
 pub fn parse_input(input: &str) -> IResult<&str, Vec<Record>> {
     let (input, _) = tuple((tag("Time:"), space0))(input)?;
     let (input, times) = separated_list1(space1, complete::i64)(input)?;
@@ -103,6 +297,138 @@ mod tests {
         assert_eq!(record.count_win_ways(), 9);
     }
 
+    #[test]
+    fn test_count_win_ways_naive() {
+        for time in 0..60 {
+            for distance in 0..(time * time / 4 + 3) {
+                let record = Record { time, distance };
+                assert_eq!(
+                    record.count_win_ways(),
+                    record.count_win_ways_naive(),
+                    "time: {}, distance: {}",
+                    time,
+                    distance
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_win_ways_large() {
+        let time = u64::MAX;
+        assert_eq!(count_win_ways(time, 0), time - 1);
+
+        // 同着は勝ちにならない
+        let time = 1u64 << 62;
+        let best = (time as u128 / 2).pow(2);
+        assert_eq!(count_win_ways(time, best), 0);
+        assert_eq!(count_win_ways(time, best - 1), 1);
+
+        // 奇数なら中央の2つが最大
+        let k = time as u128 / 2;
+        assert_eq!(count_win_ways(time + 1, k * (k + 1)), 0);
+        assert_eq!(count_win_ways(time + 1, k * (k + 1) - 1), 2);
+        assert_eq!(count_win_ways(time, u128::MAX), 0);
+    }
+
+    #[test]
+    fn test_isqrt() {
+        for n in 0..10_000u128 {
+            let s = isqrt(n);
+            assert!(s * s <= n && n < (s + 1) * (s + 1), "n: {}", n);
+        }
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+        assert_eq!(isqrt((1 << 100) - 1), (1 << 50) - 1);
+    }
+
+    #[test]
+    fn test_race_linear() {
+        let (_, records) = parse_input(INPUT).unwrap();
+
+        let expects = [(3, 12, 3, Some(2..=5)), (7, 56, 16, Some(4..=11))];
+        for (record, expected) in records.iter().zip(expects) {
+            let race = Race::from(record);
+            assert_eq!(race.optimal_press(), expected.0);
+            assert_eq!(race.best_distance(), expected.1);
+            assert_eq!(race.margin(), expected.2);
+            assert_eq!(race.winning_interval(), expected.3);
+        }
+
+        for time in 0..40 {
+            for distance in 0..(time * time / 4 + 3) {
+                let record = Record { time, distance };
+                let race = Race::from(&record);
+                assert_eq!(race.count_win_ways() as usize, record.count_win_ways());
+            }
+        }
+    }
+
+    #[test]
+    fn test_race_acceleration() {
+        // 加速度 a なら a * p * (t - p) > d
+        let race = Race {
+            time: 7,
+            distance: 9,
+            charge: Linear { acceleration: 2 },
+        };
+        assert_eq!(race.winning_interval(), Some(1..=6));
+        assert_eq!(race.count_win_ways(), count_win_ways(7, 9 / 2));
+
+        let race = Race {
+            time: 7,
+            distance: 100,
+            charge: Linear { acceleration: 2 },
+        };
+        assert_eq!(race.winning_interval(), None);
+        assert_eq!(race.margin(), 24 - 100);
+    }
+
+    #[test]
+    fn test_race_capped() {
+        let race = Race {
+            time: 10,
+            distance: 15,
+            charge: Capped {
+                acceleration: 1,
+                max_speed: 3,
+            },
+        };
+
+        // 距離: 0, 9, 16, 21, 18, 15, 12, 9, 6, 3, 0
+        assert_eq!(race.optimal_press(), 3);
+        assert_eq!(race.best_distance(), 21);
+        assert_eq!(race.margin(), 6);
+        assert_eq!(race.winning_interval(), Some(2..=4));
+
+        let brute_force = (0..=race.time)
+            .filter(|&p| race.travel(p) > race.distance)
+            .count();
+        assert_eq!(race.count_win_ways() as usize, brute_force);
+
+        // 時間を過ぎるまで押すと進めない
+        assert_eq!(race.travel(race.time), 0);
+        assert_eq!(race.travel(race.time + 1), 0);
+        assert_eq!(race.travel(u64::MAX), 0);
+    }
+
+    #[test]
+    fn test_race_merge() {
+        let (_, records) = parse_input(INPUT).unwrap();
+        let races = records.iter().map(Race::from).collect::<Vec<_>>();
+
+        let race = Race::merge(&races).unwrap();
+        assert_eq!(race.time, 71530);
+        assert_eq!(race.distance, 940200);
+        assert_eq!(race.count_win_ways(), 71503);
+
+        let race = Race::from(&Record {
+            time: 10,
+            distance: 0,
+        });
+        let races = vec![race; 25];
+        assert!(Race::merge(&races).is_err());
+    }
+
     #[test]
     fn test_merge_records() {
         let (_, records) = parse_input(INPUT).unwrap();
@@ -0,0 +1,46 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use itertools::Itertools;
+
+use day_07::{generate_hands, total_winnings, InputLine, Rules};
+
+/// 再現できる擬似乱数の手札に bid を付ける
+fn generate_input(count: usize) -> String {
+    generate_hands(&Rules::normal(), count)
+        .iter()
+        .enumerate()
+        .map(|(i, cards)| format!("{} {}", cards, i * 7919 % 1000))
+        .join("\n")
+}
+
+/// Hand の Ord で並べ替える
+fn total_winnings_by_ord(lines: &[InputLine], rules: &Rules) -> u64 {
+    lines
+        .iter()
+        .map(|line| (line.bid, rules.hand(line.cards).unwrap()))
+        .sorted_by(|(_, a), (_, b)| a.cmp(b))
+        .enumerate()
+        .map(|(i, (bid, _))| (i as u64 + 1) * bid as u64)
+        .sum()
+}
+
+fn bench_hand_order(c: &mut Criterion) {
+    let rules = Rules::joker();
+    let mut group = c.benchmark_group("total_winnings");
+
+    for count in [1_000, 100_000, 1_000_000] {
+        let input = generate_input(count);
+        let (_, lines) = day_07::parse_input(&input).unwrap();
+
+        group.bench_with_input(BenchmarkId::new("ord", count), &lines, |b, lines| {
+            b.iter(|| total_winnings_by_ord(black_box(lines), &rules))
+        });
+        group.bench_with_input(BenchmarkId::new("packed", count), &lines, |b, lines| {
+            b.iter(|| total_winnings(black_box(lines), &rules).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_hand_order);
+criterion_main!(benches);
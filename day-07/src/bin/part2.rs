@@ -1,37 +1,35 @@
 use std::fs;
 use std::io::{BufReader, Read};
 
-use itertools::Itertools;
-
-use day_07::{parse_input, Hand, JokerRule};
+use day_07::{format_table, parse_input, ranking, total_winnings, Rules};
 
 fn main() -> anyhow::Result<()> {
     let mut r = BufReader::new(fs::File::open("day-07/data/input.txt")?);
     let mut input = String::new();
     r.read_to_string(&mut input)?;
 
-    let answer = process(&input)?;
+    // 引数でルールのファイルを指定できる。--table を付けると順位表を表示する
+    let (flags, paths): (Vec<_>, Vec<_>) =
+        std::env::args().skip(1).partition(|a| a.starts_with("--"));
+    let rules = match paths.first() {
+        Some(path) => Rules::parse(&fs::read_to_string(path)?)?,
+        None => Rules::joker(),
+    };
+    let table = flags.iter().any(|f| f == "--table");
+
+    let answer = process(&input, &rules, table)?;
     println!("answer: {}", answer);
     Ok(())
 }
 
-fn process(input: &str) -> anyhow::Result<String> {
+fn process(input: &str, rules: &Rules, table: bool) -> anyhow::Result<String> {
     let (_, lines) = parse_input(input).map_err(|e| anyhow::anyhow!("{:?}", e))?;
 
-    let hands = lines
-        .iter()
-        .map(|line| (line, Hand::<JokerRule>::new(line.cards)))
-        .sorted_by(|(_, a), (_, b)| a.cmp(b))
-        .collect::<Vec<_>>();
-
-    let mut total = 0;
-    for (i, (line, hand)) in hands.iter().enumerate() {
-        let rank = (i + 1) as u32;
-
-        total += line.bid * rank;
-        println!("{}: {:?} {:?}", i, line, hand);
+    if table {
+        println!("{}", format_table(&ranking(&lines, rules)?));
     }
 
+    let total = total_winnings(&lines, rules)?;
     Ok(total.to_string())
 }
 
@@ -51,7 +49,10 @@ mod tests {
 
     #[test]
     fn test_process() {
-        let answer = process(INPUT).unwrap();
+        let answer = process(INPUT, &Rules::joker(), false).unwrap();
+        assert_eq!(answer, "5905");
+
+        let answer = process(INPUT, &Rules::joker(), true).unwrap();
         assert_eq!(answer, "5905");
     }
 }
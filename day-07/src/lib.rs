@@ -1,9 +1,9 @@
 use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::fmt;
 
-use itertools::Itertools;
+use nom::bytes::complete::take_till1;
 use nom::character::complete;
-use nom::character::complete::{alphanumeric1, newline, space1};
+use nom::character::complete::{newline, space1};
 use nom::multi::separated_list1;
 
 use nom::IResult;
@@ -14,33 +14,40 @@ pub struct InputLine<'a> {
     pub bid: u32,
 }
 
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
 pub enum HandType {
     HighCard,
     OnePair,
     TwoPair,
     ThreeOfKind,
+    /// 連続した札。Rules で有効にした場合のみ。
+    Straight,
     FullHouse,
     FourOfKind,
     FiveOfKind,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct NormalRule;
-#[derive(Debug, PartialEq, Eq)]
-pub struct JokerRule;
-
 #[derive(Debug)]
-pub struct Hand<'a, Rule> {
+pub struct Hand<'a> {
     cards: &'a str,
     hand_type: HandType,
     orders: Vec<u8>,
-    _rule: std::marker::PhantomData<Rule>,
 }
 
-pub fn parse_input(input: &str) -> IResult<&str, Vec<InputLine>> {
-    fn input_line(input: &str) -> IResult<&str, InputLine> {
-        let (input, label) = alphanumeric1(input)?;
+impl Hand<'_> {
+    pub fn cards(&self) -> &str {
+        self.cards
+    }
+
+    pub fn hand_type(&self) -> HandType {
+        self.hand_type
+    }
+}
+
+pub fn parse_input(input: &str) -> IResult<&str, Vec<InputLine<'_>>> {
+    fn input_line(input: &str) -> IResult<&str, InputLine<'_>> {
+        // ルールによっては英数字以外の札もある
+        let (input, label) = take_till1(|c: char| c.is_whitespace())(input)?;
         let (input, _) = space1(input)?;
         let (input, bid) = complete::u32(input)?;
         Ok((input, InputLine { cards: label, bid }))
@@ -50,48 +57,14 @@ pub fn parse_input(input: &str) -> IResult<&str, Vec<InputLine>> {
     Ok((input, hands))
 }
 
-impl Hand<'_, NormalRule> {
-    pub fn new(cards: &str) -> Hand<NormalRule> {
-        let hand_type = get_hand_type(cards, false);
-        let orders = cards
-            .chars()
-            .map(|c| get_order(&c, &CARD_ORDER_FOR_NORMAL_RULE))
-            .collect::<Vec<_>>();
-
-        Hand {
-            cards,
-            orders,
-            hand_type,
-            _rule: std::marker::PhantomData,
-        }
-    }
-}
-
-impl Hand<'_, JokerRule> {
-    pub fn new(cards: &str) -> Hand<JokerRule> {
-        let hand_type = get_hand_type(cards, true);
-        let orders = cards
-            .chars()
-            .map(|c| get_order(&c, &CARD_ORDER_FOR_JOKER_RULE))
-            .collect::<Vec<_>>();
-
-        Hand {
-            cards,
-            orders,
-            hand_type,
-            _rule: std::marker::PhantomData,
-        }
-    }
-}
-
-impl<Rule> PartialEq for Hand<'_, Rule> {
+impl PartialEq for Hand<'_> {
     fn eq(&self, other: &Self) -> bool {
         self.cards == other.cards
     }
 }
-impl<Rule> Eq for Hand<'_, Rule> {}
+impl Eq for Hand<'_> {}
 
-impl<Rule> Ord for Hand<'_, Rule> {
+impl Ord for Hand<'_> {
     fn cmp(&self, other: &Self) -> Ordering {
         match self.hand_type.cmp(&other.hand_type) {
             Ordering::Equal => self.orders.cmp(&other.orders),
@@ -99,103 +72,493 @@ impl<Rule> Ord for Hand<'_, Rule> {
         }
     }
 }
-impl<Rule> PartialOrd for Hand<'_, Rule> {
+impl PartialOrd for Hand<'_> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-const CARD_ORDER_FOR_NORMAL_RULE: [char; 13] = [
-    '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K', 'A',
-];
-const CARD_ORDER_FOR_JOKER_RULE: [char; 13] = [
-    'J', '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'Q', 'K', 'A',
-];
-
-fn get_order(card: &char, card_order: &[char]) -> u8 {
-    card_order
-        .iter()
-        .position(|x| x == card)
-        .map(|x| x as u8)
-        .unwrap_or_else(|| panic!("Invalid card: {}", card))
+/// 札の種類と強さ、ワイルドカード、手札の枚数、追加の役を決めるルール
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rules {
+    /// 弱い順に並べた札
+    order: Vec<char>,
+    /// 他のどの札の代わりにもなる札
+    wildcards: Vec<char>,
+    hand_size: usize,
+    /// 連続した札をストレートとして役にする
+    straights: bool,
 }
 
-fn get_counts(cards: &str, joker_rule: bool) -> HashMap<char, usize> {
-    let counts = cards
-        .chars()
-        .sorted()
-        .group_by(|&c| c)
-        .into_iter()
-        .map(|(k, v)| (k, v.count()))
-        .collect::<HashMap<char, usize>>();
+impl Rules {
+    pub fn new(
+        order: &str,
+        wildcards: &str,
+        hand_size: usize,
+        straights: bool,
+    ) -> anyhow::Result<Self> {
+        let order = order.chars().collect::<Vec<_>>();
+        let wildcards = wildcards.chars().collect::<Vec<_>>();
+
+        if order.is_empty() || order.len() > u8::MAX as usize {
+            anyhow::bail!("invalid number of cards: {}", order.len());
+        }
+        for (i, c) in order.iter().enumerate() {
+            if order[..i].contains(c) {
+                anyhow::bail!("duplicated card: {}", c);
+            }
+        }
+        if let Some(c) = wildcards.iter().find(|c| !order.contains(c)) {
+            anyhow::bail!("wildcard is not in order: {}", c);
+        }
+        for (i, c) in wildcards.iter().enumerate() {
+            if wildcards[..i].contains(c) {
+                anyhow::bail!("duplicated wildcard: {}", c);
+            }
+        }
+        // classify は札の枚数を u8 で数える
+        if hand_size == 0 || hand_size > u8::MAX as usize {
+            anyhow::bail!("invalid hand size: {}", hand_size);
+        }
 
-    if !joker_rule {
-        return counts;
+        Ok(Self {
+            order,
+            wildcards,
+            hand_size,
+            straights,
+        })
     }
 
-    // J を一番多い札に差し替える。
-    let j = *counts.get(&'J').unwrap_or(&0);
-    let max = counts
-        .iter()
-        .filter(|(c, _)| **c != 'J')
-        .max_by_key(|(_, count)| *count)
-        .map(|(k, count)| (*k, *count));
-    let Some(max) = max else { return counts };
-
-    let mut new_counts = HashMap::new();
-    for (card, count) in counts {
-        if card == 'J' {
-            continue;
+    /// part1 のルール
+    pub fn normal() -> Self {
+        Self::new("23456789TJQKA", "", 5, false).expect("valid rules")
+    }
+
+    /// part2 のルール: J は最弱で、どの札の代わりにもなる
+    pub fn joker() -> Self {
+        Self::new("J23456789TQKA", "J", 5, false).expect("valid rules")
+    }
+
+    /// "key: value" の行からルールを読み込む。
+    /// order 以外は省略でき、省略時は wildcards なし、hand_size 5、straights false。
+    ///
+    /// order: J23456789TQKA
+    /// wildcards: J
+    /// hand_size: 5
+    /// straights: false
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        let mut order = None;
+        let mut wildcards = "";
+        let mut hand_size = 5;
+        let mut straights = false;
+
+        for line in input.lines().filter(|l| !l.trim().is_empty()) {
+            let (key, value) = line
+                .split_once(':')
+                .ok_or(anyhow::anyhow!("invalid line: {:?}", line))?;
+            let value = value.trim();
+            match key.trim() {
+                "order" => order = Some(value),
+                "wildcards" => wildcards = value,
+                "hand_size" => hand_size = value.parse()?,
+                "straights" => straights = value.parse()?,
+                key => anyhow::bail!("unknown key: {}", key),
+            }
         }
 
-        if card == max.0 {
-            new_counts.insert(card, max.1 + j);
+        let order = order.ok_or(anyhow::anyhow!("order is required"))?;
+        Self::new(order, wildcards, hand_size, straights)
+    }
+
+    pub fn hand<'a>(&self, cards: &'a str) -> anyhow::Result<Hand<'a>> {
+        let orders = self.orders(cards)?;
+        let hand_type = self.classify(orders.iter().copied());
+        Ok(Hand {
+            cards,
+            hand_type,
+            orders,
+        })
+    }
+
+    pub fn hand_type(&self, cards: &str) -> anyhow::Result<HandType> {
+        Ok(self.classify(self.orders(cards)?))
+    }
+
+    /// 各札の強さ（order での位置）を返す
+    fn orders(&self, cards: &str) -> anyhow::Result<Vec<u8>> {
+        let orders = cards
+            .chars()
+            .map(|c| self.get_order(c))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if orders.len() != self.hand_size {
+            anyhow::bail!("hand should have {} cards: {:?}", self.hand_size, cards);
+        }
+        Ok(orders)
+    }
+
+    fn get_order(&self, card: char) -> anyhow::Result<u8> {
+        self.order
+            .iter()
+            .position(|&x| x == card)
+            .map(|x| x as u8)
+            .ok_or_else(|| anyhow::anyhow!("invalid card: {}", card))
+    }
+
+    fn is_wild(&self, order: u8) -> bool {
+        self.wildcards.contains(&self.order[order as usize])
+    }
+
+    /// 役を判定する。大量の手札を扱えるよう、ヒープを確保しない。
+    fn classify(&self, orders: impl IntoIterator<Item = u8>) -> HandType {
+        let mut counts = [0u8; u8::MAX as usize + 1];
+        let mut wild = 0;
+        for o in orders {
+            if self.is_wild(o) {
+                wild += 1;
+            } else {
+                counts[o as usize] += 1;
+            }
+        }
+
+        let straight = self.straights && self.is_straight(&counts, wild);
+
+        // ワイルドカードは一番多い札に加える。
+        let (mut first, mut second) = (0, 0);
+        for &count in &counts[..self.order.len()] {
+            if count > first {
+                second = first;
+                first = count;
+            } else if count > second {
+                second = count;
+            }
+        }
+        let first = first as usize + wild;
+
+        let hand_type = match (first, second) {
+            (5.., _) => HandType::FiveOfKind,
+            (4, _) => HandType::FourOfKind,
+            (3, 2..) => HandType::FullHouse,
+            (3, _) => HandType::ThreeOfKind,
+            (2, 2..) => HandType::TwoPair,
+            (2, _) => HandType::OnePair,
+            _ => HandType::HighCard,
+        };
+
+        if straight {
+            hand_type.max(HandType::Straight)
         } else {
-            new_counts.insert(card, count);
+            hand_type
+        }
+    }
+
+    /// ワイルドカードで隙間を埋めて、手札の枚数だけ連続した札にできるか
+    fn is_straight(&self, counts: &[u8], wild: usize) -> bool {
+        if self.order.len() - self.wildcards.len() < self.hand_size {
+            return false;
+        }
+
+        // ワイルドカードを除いた並びでの、最も弱い札と強い札の位置
+        let mut range = None;
+        let naturals = (0..self.order.len()).filter(|&o| !self.is_wild(o as u8));
+        for (i, o) in naturals.enumerate() {
+            match counts[o] {
+                0 => continue,
+                1 => range = Some((range.map_or(i, |(low, _)| low), i)),
+                _ => return false,
+            }
+        }
+
+        match range {
+            Some((low, high)) => high - low < self.hand_size,
+            None => wild == self.hand_size,
+        }
+    }
+
+    /// 札1枚の強さを表すのに必要なビット数
+    fn card_bits(&self) -> u32 {
+        u32::BITS - (self.order.len() as u32 - 1).leading_zeros()
+    }
+
+    /// 役と札の強さを一つの整数に詰めた値。大小関係は Hand の並び順と一致する。
+    /// 上位に役、下位に札の強さを先頭の札から順に並べる。
+    pub fn key(&self, cards: &str) -> anyhow::Result<u64> {
+        let bits = self.card_bits();
+        if HAND_TYPE_BITS + bits * self.hand_size as u32 > u64::BITS {
+            anyhow::bail!("hand is too large to pack into u64");
+        }
+
+        let mut orders = 0u64;
+        let mut len = 0;
+        for c in cards.chars() {
+            len += 1;
+            if len > self.hand_size {
+                break;
+            }
+            orders = orders << bits | self.get_order(c)? as u64;
         }
+        if len != self.hand_size {
+            anyhow::bail!("hand should have {} cards: {:?}", self.hand_size, cards);
+        }
+
+        let mask = (1u64 << bits) - 1;
+        let hand_type = self.classify((0..len).map(|i| (orders >> (bits * i as u32) & mask) as u8));
+
+        Ok((hand_type as u64) << (bits * len as u32) | orders)
     }
-    new_counts
 }
 
-fn get_hand_type(cards: &str, joker_rule: bool) -> HandType {
-    let counts = get_counts(cards, joker_rule);
-    println!("{:?}", counts);
+/// 手札の役と、ワイルドカードを置き換えた具体的な手札
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Explanation {
+    pub cards: String,
+    /// ワイルドカードを置き換えた後の手札。
+    /// 同じ役同士の比較では、置き換える前の札の強さを使う。
+    pub substituted: String,
+    pub hand_type: HandType,
+}
+
+/// 2つの手札の勝敗を決めた理由
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TieBreak {
+    /// 役の強さで決まった
+    HandType(HandType, HandType),
+    /// 役が同じで、index 番目の札の強さで決まった
+    Card { index: usize, cards: (char, char) },
+    /// すべて同じ強さ
+    Equal,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Comparison {
+    pub ordering: Ordering,
+    pub reason: TieBreak,
+}
 
-    // Five of a kind
-    if counts.len() == 1 {
-        return HandType::FiveOfKind;
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match self.ordering {
+            Ordering::Less => "<",
+            Ordering::Equal => "=",
+            Ordering::Greater => ">",
+        };
+        match self.reason {
+            TieBreak::HandType(a, b) => write!(f, "{:?} {} {:?}", a, sign, b),
+            TieBreak::Card { index, cards } => write!(
+                f,
+                "same type, card {}: {} {} {}",
+                index + 1,
+                cards.0,
+                sign,
+                cards.1
+            ),
+            TieBreak::Equal => write!(f, "same strength"),
+        }
     }
+}
+
+impl Rules {
+    /// ワイルドカードを、役が最も強くなる具体的な札に置き換えた手札を返す
+    pub fn substitute(&self, cards: &str) -> anyhow::Result<String> {
+        let orders = self.orders(cards)?;
+        let hand_type = self.classify(orders.iter().copied());
+        let wild = orders.iter().filter(|&&o| self.is_wild(o)).count();
+
+        // ワイルドカードを除いた札を弱い順に
+        let naturals = (0..self.order.len() as u8)
+            .filter(|&o| !self.is_wild(o))
+            .collect::<Vec<_>>();
+        if wild == 0 || naturals.is_empty() {
+            return Ok(cards.to_string());
+        }
 
-    // Four of a kind
-    if counts.len() == 2 && counts.iter().any(|(_, count)| *count == 4) {
-        return HandType::FourOfKind;
+        let fills = if hand_type == HandType::Straight {
+            // 足りない札を埋めて、できるだけ強いストレートにする
+            let positions = orders
+                .iter()
+                .filter_map(|o| naturals.iter().position(|n| n == o))
+                .collect::<Vec<_>>();
+            let top = naturals.len() - self.hand_size;
+            let start = positions.iter().min().map_or(top, |&low| low.min(top));
+            (start..start + self.hand_size)
+                .filter(|i| !positions.contains(i))
+                .map(|i| naturals[i])
+                .collect::<Vec<_>>()
+        } else {
+            // 一番多い札（同数なら強い札）に揃える
+            let target = naturals
+                .iter()
+                .copied()
+                .filter(|n| orders.contains(n))
+                .max_by_key(|n| (orders.iter().filter(|o| *o == n).count(), *n))
+                .unwrap_or(naturals[naturals.len() - 1]);
+            vec![target; wild]
+        };
+
+        let mut fills = fills.into_iter();
+        Ok(orders
+            .iter()
+            .map(|&o| match self.is_wild(o) {
+                true => self.order[fills.next().unwrap_or(o) as usize],
+                false => self.order[o as usize],
+            })
+            .collect())
     }
 
-    // Full house
-    if counts.len() == 2
-        && counts.iter().any(|(_, count)| *count == 3)
-        && counts.iter().any(|(_, count)| *count == 2)
-    {
-        return HandType::FullHouse;
+    pub fn explain(&self, cards: &str) -> anyhow::Result<Explanation> {
+        Ok(Explanation {
+            cards: cards.to_string(),
+            substituted: self.substitute(cards)?,
+            hand_type: self.hand_type(cards)?,
+        })
     }
 
-    // Three of a kind
-    if counts.len() == 3 && counts.iter().any(|(_, count)| *count == 3) {
-        return HandType::ThreeOfKind;
+    /// 2つの手札を比べ、勝敗を決めた理由を返す
+    pub fn compare(&self, a: &str, b: &str) -> anyhow::Result<Comparison> {
+        let (a, b) = (self.hand(a)?, self.hand(b)?);
+        let ordering = a.cmp(&b);
+
+        let reason = if a.hand_type != b.hand_type {
+            TieBreak::HandType(a.hand_type, b.hand_type)
+        } else {
+            a.orders
+                .iter()
+                .zip(&b.orders)
+                .position(|(x, y)| x != y)
+                .map_or(TieBreak::Equal, |index| TieBreak::Card {
+                    index,
+                    cards: (
+                        self.order[a.orders[index] as usize],
+                        self.order[b.orders[index] as usize],
+                    ),
+                })
+        };
+
+        Ok(Comparison { ordering, reason })
     }
+}
 
-    // Two pair
-    if counts.len() == 3 && counts.iter().any(|(_, count)| *count == 2) {
-        return HandType::TwoPair;
+/// 順位をつけた手札
+#[derive(Debug)]
+pub struct RankedHand<'a> {
+    pub rank: usize,
+    pub line: &'a InputLine<'a>,
+    pub explanation: Explanation,
+}
+
+impl RankedHand<'_> {
+    /// bid × 順位
+    pub fn winnings(&self) -> u64 {
+        self.rank as u64 * self.line.bid as u64
+    }
+}
+
+/// 手札を弱い順に並べ、順位をつける
+pub fn ranking<'a>(
+    lines: &'a [InputLine<'a>],
+    rules: &Rules,
+) -> anyhow::Result<Vec<RankedHand<'a>>> {
+    let mut hands = lines
+        .iter()
+        .map(|line| Ok((rules.hand(line.cards)?, line)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    hands.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    hands
+        .into_iter()
+        .enumerate()
+        .map(|(i, (_, line))| {
+            Ok(RankedHand {
+                rank: i + 1,
+                line,
+                explanation: rules.explain(line.cards)?,
+            })
+        })
+        .collect()
+}
+
+/// 順位、手札、役、bid、獲得額を表にする
+pub fn format_table(ranking: &[RankedHand]) -> String {
+    let mut table = format!(
+        "{:>6}  {:<8}  {:<8}  {:<12}  {:>6}  {:>10}\n",
+        "rank", "cards", "as", "type", "bid", "winnings"
+    );
+    for hand in ranking {
+        table += &format!(
+            "{:>6}  {:<8}  {:<8}  {:<12}  {:>6}  {:>10}\n",
+            hand.rank,
+            hand.line.cards,
+            hand.explanation.substituted,
+            format!("{:?}", hand.explanation.hand_type),
+            hand.line.bid,
+            hand.winnings()
+        );
     }
+    table
+}
+
+/// HandType を表すのに必要なビット数
+const HAND_TYPE_BITS: u32 = 3;
+
+/// キーの下位から8ビットずつ安定に並べ替える（LSD 基数ソート）
+pub fn radix_sort<T: Copy>(items: &mut [(u64, T)]) {
+    let Some(max) = items.iter().map(|(key, _)| *key).max() else {
+        return;
+    };
+    let passes = (u64::BITS - max.leading_zeros()).div_ceil(8);
+
+    let mut buffer = items.to_vec();
+    for pass in 0..passes {
+        let shift = pass * 8;
+
+        let mut offsets = [0usize; 257];
+        for (key, _) in items.iter() {
+            offsets[(key >> shift & 0xff) as usize + 1] += 1;
+        }
+        for i in 0..256 {
+            offsets[i + 1] += offsets[i];
+        }
 
-    // One pair
-    if counts.iter().any(|(_, count)| *count == 2) {
-        return HandType::OnePair;
+        for item in items.iter() {
+            let bucket = (item.0 >> shift & 0xff) as usize;
+            buffer[offsets[bucket]] = *item;
+            offsets[bucket] += 1;
+        }
+        items.copy_from_slice(&buffer);
     }
+}
+
+/// 手札を弱い順に並べたときの、bid × 順位の合計
+pub fn total_winnings(lines: &[InputLine], rules: &Rules) -> anyhow::Result<u64> {
+    let mut keys = lines
+        .iter()
+        .map(|line| Ok((rules.key(line.cards)?, line.bid)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    radix_sort(&mut keys);
+
+    Ok(keys
+        .iter()
+        .enumerate()
+        .map(|(i, (_, bid))| (i as u64 + 1) * *bid as u64)
+        .sum())
+}
 
-    // High card
-    HandType::HighCard
+/// 再現できる擬似乱数で手札を作る。テストとベンチマーク用。
+#[doc(hidden)]
+pub fn generate_hands(rules: &Rules, count: usize) -> Vec<String> {
+    let mut seed = 0x2545_f491_4f6c_dd1du64;
+    (0..count)
+        .map(|_| {
+            (0..rules.hand_size)
+                .map(|_| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 7;
+                    seed ^= seed << 17;
+                    rules.order[(seed % rules.order.len() as u64) as usize]
+                })
+                .collect()
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -244,7 +607,12 @@ mod tests {
         ];
 
         for (cards, expected) in patterns {
-            assert_eq!(get_hand_type(cards, false), expected, "{:?}", cards)
+            assert_eq!(
+                Rules::normal().hand_type(cards).unwrap(),
+                expected,
+                "{:?}",
+                cards
+            )
         }
     }
 
@@ -261,7 +629,12 @@ mod tests {
         ];
 
         for (cards, expected) in patterns {
-            assert_eq!(get_hand_type(cards, true), expected, "{:?}", cards)
+            assert_eq!(
+                Rules::joker().hand_type(cards).unwrap(),
+                expected,
+                "{:?}",
+                cards
+            )
         }
     }
 
@@ -271,6 +644,7 @@ mod tests {
             HandType::FullHouse,
             HandType::ThreeOfKind,
             HandType::OnePair,
+            HandType::Straight,
             HandType::HighCard,
             HandType::FiveOfKind,
             HandType::TwoPair,
@@ -285,6 +659,7 @@ mod tests {
                 HandType::OnePair,
                 HandType::TwoPair,
                 HandType::ThreeOfKind,
+                HandType::Straight,
                 HandType::FullHouse,
                 HandType::FourOfKind,
                 HandType::FiveOfKind,
@@ -294,15 +669,218 @@ mod tests {
 
     #[test]
     fn test_hand_order() {
-        assert!(Hand::<NormalRule>::new("AKQJT") > Hand::<NormalRule>::new("2KQJT"));
-        assert!(Hand::<NormalRule>::new("33332") > Hand::<NormalRule>::new("2AAAA"));
-        assert!(Hand::<NormalRule>::new("T55J5") > Hand::<NormalRule>::new("KTJJT"));
+        let rules = Rules::normal();
+        let hand = |cards| rules.hand(cards).unwrap();
+
+        assert!(hand("AKQJT") > hand("2KQJT"));
+        assert!(hand("33332") > hand("2AAAA"));
+        assert!(hand("T55J5") > hand("KTJJT"));
     }
 
     #[test]
     fn test_hand_order_j() {
-        assert!(Hand::<JokerRule>::new("AKQJT") > Hand::<JokerRule>::new("2KQJT"));
-        assert!(Hand::<JokerRule>::new("33332") > Hand::<JokerRule>::new("2AAAA"));
-        assert!(Hand::<JokerRule>::new("T55J5") < Hand::<JokerRule>::new("KTJJT"));
+        let rules = Rules::joker();
+        let hand = |cards| rules.hand(cards).unwrap();
+
+        assert!(hand("AKQJT") > hand("2KQJT"));
+        assert!(hand("33332") > hand("2AAAA"));
+        assert!(hand("T55J5") < hand("KTJJT"));
+    }
+
+    #[test]
+    fn test_invalid_hand() {
+        assert!(Rules::normal().hand("AKQJX").is_err());
+        assert!(Rules::normal().hand("AKQJ").is_err());
+        assert!(Rules::normal().hand("AKQJTT").is_err());
+    }
+
+    #[test]
+    fn test_rules_parse() {
+        let rules = Rules::parse(indoc! {r#"
+            order: J23456789TQKA
+            wildcards: J
+            "#})
+        .unwrap();
+        assert_eq!(rules, Rules::joker());
+
+        assert!(Rules::parse("wildcards: J").is_err());
+        assert!(Rules::parse("order: 23456789TQKA\nwildcards: J").is_err());
+        assert!(Rules::parse("order: 2234").is_err());
+        assert!(Rules::parse("order: 234\ncolor: red").is_err());
+        assert!(Rules::parse("order: 234\nhand_size: 0").is_err());
+        assert!(Rules::parse("order: 234\nhand_size: 256").is_err());
+        assert!(Rules::parse("order: 234\nhand_size: 255").is_ok());
+        assert!(Rules::parse("order: J2\nwildcards: JJJ\nhand_size: 2\nstraights: true").is_err());
+    }
+
+    #[test]
+    fn test_parse_input_symbols() {
+        let (_, lines) = parse_input("*?1 5\n12* 7").unwrap();
+        assert_eq!(
+            lines,
+            [
+                InputLine {
+                    cards: "*?1",
+                    bid: 5
+                },
+                InputLine {
+                    cards: "12*",
+                    bid: 7
+                },
+            ]
+        );
+
+        // どちらもストレートで、先頭の札が強い 12* が勝つ
+        let rules = Rules::new("*?123456789", "*?", 3, true).unwrap();
+        assert_eq!(total_winnings(&lines, &rules).unwrap(), 5 + 7 * 2);
+    }
+
+    #[test]
+    fn test_rules_custom() {
+        // 3枚の手札、ワイルドカード2種類、ストレートあり
+        let rules = Rules::new("*?123456789", "*?", 3, true).unwrap();
+
+        #[rustfmt::skip]
+        let patterns = [
+            ("111", HandType::ThreeOfKind),
+            ("11*", HandType::ThreeOfKind),
+            ("1*?", HandType::Straight),
+            ("123", HandType::Straight),
+            ("1*3", HandType::Straight),
+            ("9*?", HandType::Straight),
+            ("135", HandType::HighCard),
+            ("112", HandType::OnePair),
+        ];
+
+        for (cards, expected) in patterns {
+            assert_eq!(rules.hand_type(cards).unwrap(), expected, "{:?}", cards)
+        }
+
+        // ストレートはスリーカードより強い
+        let rules = Rules::new("23456789TJQKA", "", 5, true).unwrap();
+        assert_eq!(rules.hand_type("9TJQK").unwrap(), HandType::Straight);
+        assert_eq!(rules.hand_type("9TJQA").unwrap(), HandType::HighCard);
+        assert!(rules.hand("23456").unwrap() > rules.hand("AAAKQ").unwrap());
+        assert!(rules.hand("23456").unwrap() < rules.hand("22233").unwrap());
+    }
+
+    #[test]
+    fn test_key_order() {
+        let straights = Rules::new("J23456789TQKA", "J", 5, true).unwrap();
+        for rules in [Rules::normal(), Rules::joker(), straights] {
+            let hands = generate_hands(&rules, 2000);
+            for (a, b) in hands.iter().tuple_windows() {
+                let ordering = rules.hand(a).unwrap().cmp(&rules.hand(b).unwrap());
+                let key_ordering = rules.key(a).unwrap().cmp(&rules.key(b).unwrap());
+                assert_eq!(ordering, key_ordering, "{} {}", a, b);
+            }
+        }
+
+        assert!(Rules::normal().key("AKQJX").is_err());
+        assert!(Rules::normal().key("AKQJ").is_err());
+        assert!(Rules::normal().key("AKQJTT").is_err());
+        assert!(Rules::new("23456789TJQKA", "", 16, false)
+            .unwrap()
+            .key("2222222222222222")
+            .is_err());
+    }
+
+    #[test]
+    fn test_radix_sort() {
+        let mut items = generate_hands(&Rules::normal(), 1000)
+            .iter()
+            .enumerate()
+            .map(|(i, hand)| (Rules::normal().key(hand).unwrap(), i))
+            .collect::<Vec<_>>();
+        let mut expected = items.clone();
+        expected.sort_by_key(|(key, _)| *key);
+
+        radix_sort(&mut items);
+        assert_eq!(items, expected);
+    }
+
+    #[test]
+    fn test_total_winnings() {
+        let (_, lines) = parse_input(INPUT).unwrap();
+        assert_eq!(total_winnings(&lines, &Rules::normal()).unwrap(), 6440);
+        assert_eq!(total_winnings(&lines, &Rules::joker()).unwrap(), 5905);
+    }
+
+    #[test]
+    fn test_substitute() {
+        let rules = Rules::joker();
+
+        #[rustfmt::skip]
+        let patterns = [
+            ("32T3K", "32T3K", HandType::OnePair),
+            ("T55J5", "T5555", HandType::FourOfKind),
+            ("KTJJT", "KTTTT", HandType::FourOfKind),
+            ("QQQJA", "QQQQA", HandType::FourOfKind),
+            ("JJJJJ", "AAAAA", HandType::FiveOfKind),
+            ("2J3J4", "24344", HandType::ThreeOfKind),
+            ("J2345", "52345", HandType::OnePair),
+        ];
+
+        for (cards, substituted, hand_type) in patterns {
+            let explanation = rules.explain(cards).unwrap();
+            assert_eq!(explanation.substituted, substituted, "{:?}", cards);
+            assert_eq!(explanation.hand_type, hand_type, "{:?}", cards);
+            assert_eq!(rules.hand_type(substituted).unwrap(), hand_type);
+        }
+    }
+
+    #[test]
+    fn test_substitute_straight() {
+        let rules = Rules::new("J23456789TQKA", "J", 5, true).unwrap();
+
+        #[rustfmt::skip]
+        let patterns = [
+            ("2J4J6", "23456"),
+            ("J2345", "62345"),
+            ("KJQJA", "K9QTA"),
+            ("2345J", "23456"),
+        ];
+
+        for (cards, substituted) in patterns {
+            assert_eq!(rules.substitute(cards).unwrap(), substituted, "{:?}", cards);
+            assert_eq!(rules.hand_type(substituted).unwrap(), HandType::Straight);
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let rules = Rules::joker();
+
+        let comparison = rules.compare("KTJJT", "T55J5").unwrap();
+        assert_eq!(comparison.ordering, Ordering::Greater);
+        assert_eq!(
+            comparison.reason,
+            TieBreak::Card {
+                index: 0,
+                cards: ('K', 'T')
+            }
+        );
+        assert_eq!(comparison.to_string(), "same type, card 1: K > T");
+
+        let comparison = rules.compare("32T3K", "KK677").unwrap();
+        assert_eq!(comparison.ordering, Ordering::Less);
+        assert_eq!(comparison.to_string(), "OnePair < TwoPair");
+
+        let comparison = rules.compare("QQQJA", "QQQJA").unwrap();
+        assert_eq!(comparison.reason, TieBreak::Equal);
+    }
+
+    #[test]
+    fn test_ranking() {
+        let (_, lines) = parse_input(INPUT).unwrap();
+        let ranking = ranking(&lines, &Rules::joker()).unwrap();
+
+        let cards = ranking.iter().map(|h| h.line.cards).collect::<Vec<_>>();
+        assert_eq!(cards, ["32T3K", "KK677", "T55J5", "QQQJA", "KTJJT"]);
+        assert_eq!(ranking.iter().map(|h| h.winnings()).sum::<u64>(), 5905);
+
+        let table = format_table(&ranking);
+        assert_eq!(table.lines().count(), 6);
+        assert!(table.lines().nth(5).unwrap().contains("KTTTT"));
     }
 }
@@ -1,14 +1,7 @@
-use std::collections::HashMap;
 use std::fs;
 use std::io::{BufReader, Read};
 
-use nom::branch::alt;
-use nom::bytes::complete::tag;
-use nom::character::complete;
-use nom::character::complete::newline;
-use nom::combinator::value;
-use nom::multi::{many1, separated_list1};
-use nom::IResult;
+use day_08::{parse_input, Network};
 
 fn main() -> anyhow::Result<()> {
     let mut r = BufReader::new(fs::File::open("day-08/data/input.txt")?);
@@ -22,82 +15,22 @@ fn main() -> anyhow::Result<()> {
 
 fn process(input: &str) -> anyhow::Result<String> {
     let (_, data) = parse_input(input).map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    let network = Network::compile(&data)?;
 
-    let mut step = 0;
-    let mut current = Place("AAA");
+    let start = network.id("AAA").ok_or(anyhow::anyhow!("no AAA"))?;
+    let goal = network.id("ZZZ").ok_or(anyhow::anyhow!("no ZZZ"))?;
+    let instructions = network.instructions();
 
-    while current != Place("ZZZ") {
-        let (left, right) = &data.places[&current];
-        let nav = &data.instructions[step % data.instructions.len()];
+    let mut step = 0;
+    let mut current = start;
+    while current != goal {
+        current = network.next(current, instructions[step % instructions.len()]);
         step += 1;
-        println!("step: {}, current: {:?}, nav: {:?}", step, current, nav);
-
-        current = match nav {
-            Navigation::Left => left.clone(),
-            Navigation::Right => right.clone(),
-        };
     }
 
     Ok(step.to_string())
 }
 
-#[derive(Debug, Hash, Eq, PartialEq, Clone)]
-struct Place<'a>(&'a str);
-
-#[derive(Debug, Copy, Clone)]
-enum Navigation {
-    Left,
-    Right,
-}
-
-#[derive(Debug)]
-struct InputData<'a> {
-    instructions: Vec<Navigation>,
-    places: HashMap<Place<'a>, (Place<'a>, Place<'a>)>,
-}
-
-fn parse_instructions(input: &str) -> IResult<&str, Vec<Navigation>> {
-    let nav = alt((
-        value(Navigation::Left, complete::char('L')),
-        value(Navigation::Right, complete::char('R')),
-    ));
-
-    many1(nav)(input)
-}
-
-fn parse_input(input: &str) -> IResult<&str, InputData> {
-    fn parse_place(input: &str) -> IResult<&str, Place> {
-        let (input, s) = complete::alpha1(input)?;
-        Ok((input, Place(s)))
-    }
-    fn parse_line(input: &str) -> IResult<&str, (Place, (Place, Place))> {
-        let (input, ret) = parse_place(input)?;
-        let (input, _) = tag(" = (")(input)?;
-        let (input, left) = parse_place(input)?;
-        let (input, _) = tag(", ")(input)?;
-        let (input, right) = parse_place(input)?;
-        let (input, _) = tag(")")(input)?;
-
-        Ok((input, (ret, (left, right))))
-    }
-    fn parse_places(input: &str) -> IResult<&str, HashMap<Place, (Place, Place)>> {
-        let (input, maps) = separated_list1(newline, parse_line)(input)?;
-        Ok((input, maps.into_iter().collect()))
-    }
-
-    let (input, instructions) = parse_instructions(input)?;
-    let (input, _) = many1(newline)(input)?;
-    let (input, places) = parse_places(input)?;
-
-    Ok((
-        input,
-        InputData {
-            instructions,
-            places,
-        },
-    ))
-}
-
 #[cfg(test)]
 mod tests {
     use indoc::indoc;
@@ -1,124 +1,57 @@
-use std::collections::HashMap;
 use std::fs;
 use std::io::{BufReader, Read};
-use std::ops::{Div, Mul, Rem};
 
-use nom::branch::alt;
-use nom::bytes::complete::tag;
-use nom::character::complete;
-use nom::character::complete::newline;
-use nom::combinator::value;
-use nom::multi::{many1, separated_list1};
-use nom::sequence::separated_pair;
-use nom::IResult;
+use day_08::{first_meeting, parse_input, Analysis, Network};
 
 fn main() -> anyhow::Result<()> {
     let mut r = BufReader::new(fs::File::open("day-08/data/input.txt")?);
     let mut input = String::new();
     r.read_to_string(&mut input)?;
 
-    let answer = process(&input)?;
+    // 引数を指定すると、そのファイルに DOT 形式の地図を書き出す
+    let dot = std::env::args().nth(1);
+
+    let answer = process(&input, dot.as_deref())?;
     println!("answer: {}", answer);
     Ok(())
 }
 
-fn find_step(data: &InputData, begin_place: &Place) -> usize {
-    let mut step = 0;
-    let mut current = begin_place;
-    while !current.ends_with('Z') {
-        let nav = &data.instructions[step % data.instructions.len()];
-        step += 1;
-
-        let (left, right) = &data.places[current];
-        current = match nav {
-            Navigation::Left => left,
-            Navigation::Right => right,
-        };
-    }
-    step
-}
-
-fn process(input: &str) -> anyhow::Result<String> {
+fn process(input: &str, dot: Option<&str>) -> anyhow::Result<String> {
     let (_, data) = parse_input(input).map_err(|e| anyhow::anyhow!("{:?}", e))?;
-
-    let numbers = data
-        .places
-        .keys()
-        .filter(|p| p.ends_with('A'))
-        .map(|p| find_step(&data, p))
-        .collect::<Vec<_>>();
-
-    let mut v = numbers[0];
-    for n in numbers.iter().skip(1) {
-        v = lcm(v, *n);
+    let network = Network::compile(&data)?;
+    let analysis = Analysis::new(&network);
+
+    println!(
+        "places: {}, components: {}",
+        network.len(),
+        analysis.components.len()
+    );
+    for ghost in &analysis.ghosts {
+        println!(
+            "{}: reachable {}, tail {}, period {}, loop {} places, goals {:?}",
+            network.name(ghost.start),
+            ghost.reachable.len(),
+            ghost.cycle.tail,
+            ghost.cycle.period,
+            ghost.loop_nodes.len(),
+            ghost
+                .loop_goals
+                .iter()
+                .map(|&node| network.name(node))
+                .collect::<Vec<_>>(),
+        );
     }
-    println!("lcm: {}", v);
-
-    Ok(v.to_string())
-}
-
-type Place<'a> = &'a str;
-
-#[derive(Debug, Copy, Clone)]
-enum Navigation {
-    Left,
-    Right,
-}
-
-#[derive(Debug)]
-struct InputData<'a> {
-    instructions: Vec<Navigation>,
-    places: HashMap<Place<'a>, (Place<'a>, Place<'a>)>,
-}
-
-fn parse_input(input: &str) -> IResult<&str, InputData> {
-    fn navs(input: &str) -> IResult<&str, Vec<Navigation>> {
-        let nav = alt((
-            value(Navigation::Left, complete::char('L')),
-            value(Navigation::Right, complete::char('R')),
-        ));
-        many1(nav)(input)
-    }
-    fn place(input: &str) -> IResult<&str, Place> {
-        complete::alphanumeric1(input)
-    }
-    fn line(input: &str) -> IResult<&str, (Place, (Place, Place))> {
-        let (input, from) = place(input)?;
-        let (input, _) = tag(" = (")(input)?;
-        let (input, (left, right)) = separated_pair(place, tag(", "), place)(input)?;
-        let (input, _) = tag(")")(input)?;
-        Ok((input, (from, (left, right))))
-    }
-
-    let (input, instructions) = navs(input)?;
-    let (input, _) = many1(newline)(input)?;
-    let (input, places) = separated_list1(newline, line)(input)?;
-
-    Ok((
-        input,
-        InputData {
-            instructions,
-            places: places.into_iter().collect(),
-        },
-    ))
-}
-
-trait Num:
-    Copy + Eq + Default + Div<Self, Output = Self> + Mul<Self, Output = Self> + Rem<Self, Output = Self>
-{
-}
-impl Num for usize {}
-impl Num for u32 {}
-
-fn gcd<T: Num>(mut a: T, mut b: T) -> T {
-    while b != Default::default() {
-        (a, b) = (b, a % b);
+    if let Some(path) = dot {
+        fs::write(path, analysis.to_dot(&network))?;
     }
-    a
-}
 
-fn lcm<T: Num>(a: T, b: T) -> T {
-    a * b / gcd(a, b)
+    let cycles = analysis
+        .ghosts
+        .into_iter()
+        .map(|ghost| ghost.cycle)
+        .collect::<Vec<_>>();
+    let step = first_meeting(&cycles).ok_or(anyhow::anyhow!("ghosts never meet"))?;
+    Ok(step.to_string())
 }
 
 #[cfg(test)]
@@ -168,18 +101,13 @@ mod tests {
 
     #[test]
     fn test_process() {
-        let answer = process(INPUT3).unwrap();
+        let answer = process(INPUT3, None).unwrap();
         assert_eq!(answer, "6");
     }
 
     #[test]
-    fn test_gdc() {
-        let numbers = [8u32, 10, 15];
-        let mut v = numbers[0];
-        for i in 1..numbers.len() {
-            v = lcm(v, numbers[i]);
-        }
-
-        assert_eq!(v, 120);
+    fn test_process_single_ghost() {
+        assert_eq!(process(INPUT, None).unwrap(), "2");
+        assert_eq!(process(INPUT2, None).unwrap(), "6");
     }
 }
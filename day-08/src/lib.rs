@@ -1 +1,810 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::ops::{Div, Mul, Rem};
 
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete;
+use nom::character::complete::newline;
+use nom::combinator::value;
+use nom::multi::{many1, separated_list1};
+use nom::sequence::separated_pair;
+use nom::IResult;
+
+pub type Place<'a> = &'a str;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Navigation {
+    Left = 0,
+    Right = 1,
+}
+
+#[derive(Debug)]
+pub struct InputData<'a> {
+    pub instructions: Vec<Navigation>,
+    pub places: HashMap<Place<'a>, (Place<'a>, Place<'a>)>,
+}
+
+pub fn parse_input(input: &str) -> IResult<&str, InputData<'_>> {
+    fn navs(input: &str) -> IResult<&str, Vec<Navigation>> {
+        let nav = alt((
+            value(Navigation::Left, complete::char('L')),
+            value(Navigation::Right, complete::char('R')),
+        ));
+        many1(nav)(input)
+    }
+    fn place(input: &str) -> IResult<&str, Place<'_>> {
+        complete::alphanumeric1(input)
+    }
+    fn line(input: &str) -> IResult<&str, (Place<'_>, (Place<'_>, Place<'_>))> {
+        let (input, from) = place(input)?;
+        let (input, _) = tag(" = (")(input)?;
+        let (input, (left, right)) = separated_pair(place, tag(", "), place)(input)?;
+        let (input, _) = tag(")")(input)?;
+        Ok((input, (from, (left, right))))
+    }
+
+    let (input, instructions) = navs(input)?;
+    let (input, _) = many1(newline)(input)?;
+    let (input, places) = separated_list1(newline, line)(input)?;
+
+    Ok((
+        input,
+        InputData {
+            instructions,
+            places: places.into_iter().collect(),
+        },
+    ))
+}
+
+/// 1体のゴーストの動き。
+/// (場所, 命令の位置) の状態は tail 歩目以降、period 歩ごとに繰り返す。
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Cycle {
+    pub tail: u64,
+    pub period: u64,
+    /// tail 歩目より前にゴールに着いた歩数
+    pub tail_hits: Vec<u64>,
+    /// 周期の中でゴールに着いた歩数（tail 以上 tail + period 未満）
+    pub cycle_hits: Vec<u64>,
+}
+
+impl Cycle {
+    /// step 歩目にゴールにいるかどうか
+    pub fn hits_at(&self, step: u64) -> bool {
+        if step < self.tail {
+            return self.tail_hits.contains(&step);
+        }
+        let offset = self.tail + (step - self.tail) % self.period;
+        self.cycle_hits.contains(&offset)
+    }
+}
+
+/// 場所を u32 の番号にした地図
+#[derive(Debug)]
+pub struct Network<'a> {
+    names: Vec<Place<'a>>,
+    ids: HashMap<Place<'a>, u32>,
+    edges: Vec<[u32; 2]>,
+    instructions: Vec<Navigation>,
+    /// walks[i * 場所の数 + node]: 命令の先頭から i 歩進んだ場所 (0 <= i <= 命令の数)
+    walks: Vec<u32>,
+    /// jumps[k][node]: 命令を 2^k 周した後の場所
+    jumps: Vec<Vec<u32>>,
+}
+
+impl<'a> Network<'a> {
+    pub fn compile(data: &InputData<'a>) -> anyhow::Result<Self> {
+        if data.instructions.is_empty() {
+            anyhow::bail!("no instructions");
+        }
+
+        let mut names = data.places.keys().copied().collect::<Vec<_>>();
+        names.sort();
+        let ids = names
+            .iter()
+            .enumerate()
+            .map(|(i, &name)| (name, i as u32))
+            .collect::<HashMap<_, _>>();
+        let id = |place: Place| {
+            ids.get(place)
+                .copied()
+                .ok_or_else(|| anyhow::anyhow!("unknown place: {}", place))
+        };
+        let edges = names
+            .iter()
+            .map(|name| {
+                let (left, right) = data.places[name];
+                Ok([id(left)?, id(right)?])
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let n = names.len();
+        let mut walks = (0..n as u32).collect::<Vec<_>>();
+        for (i, &nav) in data.instructions.iter().enumerate() {
+            let next = (0..n)
+                .map(|node| edges[walks[i * n + node] as usize][nav as usize])
+                .collect::<Vec<_>>();
+            walks.extend(next);
+        }
+
+        // u64 の歩数まで届くように段を積む
+        let pass = walks[data.instructions.len() * n..].to_vec();
+        let levels = u64::BITS - (u64::MAX / data.instructions.len() as u64).leading_zeros();
+        let mut jumps = vec![pass];
+        for k in 1..levels as usize {
+            let prev = &jumps[k - 1];
+            let next = prev.iter().map(|&node| prev[node as usize]).collect();
+            jumps.push(next);
+        }
+
+        Ok(Network {
+            names,
+            ids,
+            edges,
+            instructions: data.instructions.clone(),
+            walks,
+            jumps,
+        })
+    }
+
+    /// 場所の数
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn instructions(&self) -> &[Navigation] {
+        &self.instructions
+    }
+
+    pub fn id(&self, place: Place) -> Option<u32> {
+        self.ids.get(place).copied()
+    }
+
+    pub fn name(&self, id: u32) -> Place<'a> {
+        self.names[id as usize]
+    }
+
+    /// ..A で終わる場所を名前順に返す
+    pub fn ghost_starts(&self) -> Vec<u32> {
+        (0..self.len() as u32)
+            .filter(|&id| self.name(id).ends_with('A'))
+            .collect()
+    }
+
+    /// node の (左, 右) の行き先
+    pub fn edges(&self, node: u32) -> [u32; 2] {
+        self.edges[node as usize]
+    }
+
+    /// node から nav に従って進んだ先
+    pub fn next(&self, node: u32, nav: Navigation) -> u32 {
+        self.edges[node as usize][nav as usize]
+    }
+
+    /// 命令の先頭から始めて、steps 歩進んだ場所
+    pub fn position(&self, start: u32, steps: u64) -> u32 {
+        let len = self.instructions.len() as u64;
+        let (mut passes, rest) = (steps / len, steps % len);
+
+        let mut node = start;
+        let mut k = 0;
+        while passes > 0 {
+            if passes & 1 == 1 {
+                node = self.jumps[k][node as usize];
+            }
+            passes >>= 1;
+            k += 1;
+        }
+        self.walks[rest as usize * self.len() + node as usize]
+    }
+}
+
+/// start から状態が繰り返すまで歩き、ゴールに着く歩数を調べる
+pub fn find_cycle(network: &Network, start: u32, is_goal: impl Fn(Place) -> bool) -> Cycle {
+    let len = network.instructions.len();
+    let goals = network
+        .names
+        .iter()
+        .map(|&p| is_goal(p))
+        .collect::<Vec<_>>();
+
+    // (場所, 命令の位置) ごとに最初に来た歩数
+    let mut visited = vec![u64::MAX; network.len() * len];
+    let mut hits = Vec::new();
+    let mut current = start;
+    let mut step = 0u64;
+    loop {
+        let index = step as usize % len;
+        let state = current as usize * len + index;
+        if visited[state] != u64::MAX {
+            let tail = visited[state];
+            let (tail_hits, cycle_hits) = hits.into_iter().partition(|&h| h < tail);
+            return Cycle {
+                tail,
+                period: step - tail,
+                tail_hits,
+                cycle_hits,
+            };
+        }
+        visited[state] = step;
+
+        if goals[current as usize] {
+            hits.push(step);
+        }
+        current = network.next(current, network.instructions[index]);
+        step += 1;
+    }
+}
+
+/// ..A から歩き始めたゴーストの行き先
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Ghost {
+    pub start: u32,
+    /// 命令を無視して、辿り着ける場所
+    pub reachable: Vec<u32>,
+    pub cycle: Cycle,
+    /// 周期の中で通る場所
+    pub loop_nodes: Vec<u32>,
+    /// 周期の中で通る ..Z の場所
+    pub loop_goals: Vec<u32>,
+}
+
+/// 地図の構造
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    /// 強連結成分。行き止まりの成分が先で、前の成分から後ろの成分へは進めない順
+    pub components: Vec<Vec<u32>>,
+    /// component[node]: node が属する成分の番号
+    pub component: Vec<usize>,
+    pub ghosts: Vec<Ghost>,
+}
+
+impl Analysis {
+    pub fn new(network: &Network) -> Self {
+        let components = strongly_connected_components(network);
+        let mut component = vec![0; network.len()];
+        for (i, nodes) in components.iter().enumerate() {
+            for &node in nodes {
+                component[node as usize] = i;
+            }
+        }
+
+        let ghosts = network
+            .ghost_starts()
+            .into_iter()
+            .map(|start| {
+                let cycle = find_cycle(network, start, |p| p.ends_with('Z'));
+
+                let len = network.instructions.len();
+                let mut current = network.position(start, cycle.tail);
+                let mut loop_nodes = BTreeSet::new();
+                for step in cycle.tail..cycle.tail + cycle.period {
+                    loop_nodes.insert(current);
+                    current = network.next(current, network.instructions[step as usize % len]);
+                }
+                let loop_goals = loop_nodes
+                    .iter()
+                    .copied()
+                    .filter(|&node| network.name(node).ends_with('Z'))
+                    .collect();
+
+                Ghost {
+                    start,
+                    reachable: reachable(network, start),
+                    cycle,
+                    loop_nodes: loop_nodes.into_iter().collect(),
+                    loop_goals,
+                }
+            })
+            .collect();
+
+        Analysis {
+            components,
+            component,
+            ghosts,
+        }
+    }
+
+    /// Graphviz の DOT 形式で出力する。
+    /// 2つ以上の場所を含む強連結成分は cluster にまとめ、
+    /// 各場所にはそこを周期で通るゴーストの出発地を添える。
+    pub fn to_dot(&self, network: &Network) -> String {
+        let mut on_loop = vec![Vec::new(); network.len()];
+        for ghost in &self.ghosts {
+            for &node in &ghost.loop_nodes {
+                on_loop[node as usize].push(network.name(ghost.start));
+            }
+        }
+
+        let node_line = |node: u32| {
+            let name = network.name(node);
+            let mut attrs = vec![];
+            if name.ends_with('A') {
+                attrs.push("shape=box".to_string());
+                attrs.push("color=green".to_string());
+            } else if name.ends_with('Z') {
+                attrs.push("shape=doublecircle".to_string());
+                attrs.push("color=red".to_string());
+            }
+            let loops = &on_loop[node as usize];
+            if !loops.is_empty() {
+                attrs.push(format!("xlabel=\"{}\"", loops.join(",")));
+            }
+            format!("\"{}\" [{}];", name, attrs.join(", "))
+        };
+
+        let mut dot = String::new();
+        writeln!(dot, "digraph network {{").unwrap();
+        for (i, nodes) in self.components.iter().enumerate() {
+            if nodes.len() > 1 {
+                writeln!(dot, "  subgraph cluster_{} {{", i).unwrap();
+                for &node in nodes {
+                    writeln!(dot, "    {}", node_line(node)).unwrap();
+                }
+                writeln!(dot, "  }}").unwrap();
+            } else {
+                writeln!(dot, "  {}", node_line(nodes[0])).unwrap();
+            }
+        }
+        for node in 0..network.len() as u32 {
+            let [left, right] = network.edges(node);
+            let name = network.name(node);
+            if left == right {
+                writeln!(
+                    dot,
+                    "  \"{}\" -> \"{}\" [label=\"LR\"];",
+                    name,
+                    network.name(left)
+                )
+                .unwrap();
+            } else {
+                writeln!(
+                    dot,
+                    "  \"{}\" -> \"{}\" [label=\"L\"];",
+                    name,
+                    network.name(left)
+                )
+                .unwrap();
+                writeln!(
+                    dot,
+                    "  \"{}\" -> \"{}\" [label=\"R\"];",
+                    name,
+                    network.name(right)
+                )
+                .unwrap();
+            }
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+/// 命令を無視して start から辿り着ける場所を番号順に返す
+pub fn reachable(network: &Network, start: u32) -> Vec<u32> {
+    let mut seen = vec![false; network.len()];
+    let mut stack = vec![start];
+    seen[start as usize] = true;
+    while let Some(node) = stack.pop() {
+        for next in network.edges(node) {
+            if !seen[next as usize] {
+                seen[next as usize] = true;
+                stack.push(next);
+            }
+        }
+    }
+    (0..network.len() as u32)
+        .filter(|&node| seen[node as usize])
+        .collect()
+}
+
+/// Tarjan のアルゴリズムで強連結成分を求める。
+/// 成分の中は番号順。成分は見つかった順、つまり行き止まりの成分が先で、
+/// 前の成分から後ろの成分へは進めない順に並ぶ。
+pub fn strongly_connected_components(network: &Network) -> Vec<Vec<u32>> {
+    const UNVISITED: u32 = u32::MAX;
+
+    let n = network.len();
+    let mut index = vec![UNVISITED; n];
+    let mut low = vec![0; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut counter = 0;
+
+    for root in 0..n as u32 {
+        if index[root as usize] != UNVISITED {
+            continue;
+        }
+
+        // 再帰の代わりに (場所, 次に見る辺) を積む
+        let mut calls = vec![(root, 0)];
+        index[root as usize] = counter;
+        low[root as usize] = counter;
+        counter += 1;
+        stack.push(root);
+        on_stack[root as usize] = true;
+
+        while let Some((v, edge)) = calls.last_mut() {
+            let v = *v as usize;
+            if *edge < 2 {
+                let w = network.edges(v as u32)[*edge];
+                *edge += 1;
+                if index[w as usize] == UNVISITED {
+                    index[w as usize] = counter;
+                    low[w as usize] = counter;
+                    counter += 1;
+                    stack.push(w);
+                    on_stack[w as usize] = true;
+                    calls.push((w, 0));
+                } else if on_stack[w as usize] {
+                    low[v] = low[v].min(index[w as usize]);
+                }
+                continue;
+            }
+
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                low[parent as usize] = low[parent as usize].min(low[v]);
+            }
+            if low[v] == index[v] {
+                let mut component = Vec::new();
+                loop {
+                    let w = stack.pop().unwrap();
+                    on_stack[w as usize] = false;
+                    component.push(w);
+                    if w as usize == v {
+                        break;
+                    }
+                }
+                component.sort();
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// すべてのゴーストが同時にゴールにいる最初の歩数。そうならなければ None
+pub fn first_meeting(cycles: &[Cycle]) -> Option<u64> {
+    let latest = cycles.iter().max_by_key(|c| c.tail)?;
+
+    // 一番長い tail より前なら、そのゴーストの tail の中で揃うはず
+    if let Some(&step) = latest
+        .tail_hits
+        .iter()
+        .find(|&&step| cycles.iter().all(|c| c.hits_at(step)))
+    {
+        return Some(step);
+    }
+
+    // それ以降はすべて周期的なので、合同式を組み合わせる
+    let mut solutions = vec![(0i128, 1i128)];
+    for cycle in cycles {
+        let period = cycle.period as i128;
+        solutions = solutions
+            .iter()
+            .flat_map(|&s| {
+                cycle
+                    .cycle_hits
+                    .iter()
+                    .filter_map(move |&hit| crt(s, (hit as i128 % period, period)))
+            })
+            .collect();
+        solutions.sort();
+        solutions.dedup();
+    }
+
+    // 条件を満たす、tail 以上の最小の歩数
+    let start = latest.tail as i128;
+    solutions
+        .into_iter()
+        .map(|(residue, modulus)| {
+            if residue >= start {
+                residue
+            } else {
+                residue + (start - residue + modulus - 1) / modulus * modulus
+            }
+        })
+        .min()
+        .map(|step| step as u64)
+}
+
+/// x ≡ a.0 (mod a.1) と x ≡ b.0 (mod b.1) を両方満たす x を (余り, 法) で返す。
+/// 法が互いに素でなくても、解があれば求める。
+pub fn crt(a: (i128, i128), b: (i128, i128)) -> Option<(i128, i128)> {
+    let (g, p, _) = ext_gcd(a.1, b.1);
+    if (b.0 - a.0) % g != 0 {
+        return None;
+    }
+
+    let modulus = a.1 / g * b.1;
+    let k = (b.0 - a.0) / g % (b.1 / g) * p % (b.1 / g);
+    let residue = (a.0 + a.1 * k).rem_euclid(modulus);
+    Some((residue, modulus))
+}
+
+/// a * x + b * y = gcd(a, b) となる (gcd, x, y)
+fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        return (a, 1, 0);
+    }
+    let (g, x, y) = ext_gcd(b, a % b);
+    (g, y, x - a / b * y)
+}
+
+pub trait Num:
+    Copy + Eq + Default + Div<Self, Output = Self> + Mul<Self, Output = Self> + Rem<Self, Output = Self>
+{
+}
+impl Num for usize {}
+impl Num for u32 {}
+
+pub fn gcd<T: Num>(mut a: T, mut b: T) -> T {
+    while b != Default::default() {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+pub fn lcm<T: Num>(a: T, b: T) -> T {
+    a * b / gcd(a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    const INPUT3: &str = indoc! {r#"
+    LR
+
+    11A = (11B, XXX)
+    11B = (XXX, 11Z)
+    11Z = (11B, XXX)
+    22A = (22B, XXX)
+    22B = (22C, 22C)
+    22C = (22Z, 22Z)
+    22Z = (22B, 22B)
+    XXX = (XXX, XXX)
+    "#};
+
+    fn cycles(input: &str) -> Vec<Cycle> {
+        let (_, data) = parse_input(input).unwrap();
+        let network = Network::compile(&data).unwrap();
+        network
+            .ghost_starts()
+            .into_iter()
+            .map(|start| find_cycle(&network, start, |p| p.ends_with('Z')))
+            .collect()
+    }
+
+    /// 全員を1歩ずつ動かして確かめる
+    fn brute_force(input: &str, limit: u64) -> Option<u64> {
+        let (_, data) = parse_input(input).unwrap();
+        let network = Network::compile(&data).unwrap();
+        let mut nodes = network.ghost_starts();
+        for step in 0..limit {
+            if nodes.iter().all(|&n| network.name(n).ends_with('Z')) {
+                return Some(step);
+            }
+            let nav = network.instructions()[step as usize % network.instructions().len()];
+            for node in nodes.iter_mut() {
+                *node = network.next(*node, nav);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        assert_eq!(
+            cycles(INPUT3),
+            [
+                Cycle {
+                    tail: 1,
+                    period: 2,
+                    tail_hits: vec![],
+                    cycle_hits: vec![2],
+                },
+                Cycle {
+                    tail: 1,
+                    period: 6,
+                    tail_hits: vec![],
+                    cycle_hits: vec![3, 6],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_first_meeting() {
+        assert_eq!(first_meeting(&cycles(INPUT3)), Some(6));
+    }
+
+    #[test]
+    fn test_first_meeting_unaligned() {
+        // 1, 4, 7, ... と 3, 7, 11, ... なので、歩数の lcm では求まらない
+        let input = indoc! {r#"
+        L
+
+        11A = (11Z, 11Z)
+        11Z = (11B, 11B)
+        11B = (11C, 11C)
+        11C = (11Z, 11Z)
+        22A = (22B, 22B)
+        22B = (22C, 22C)
+        22C = (22Z, 22Z)
+        22Z = (22D, 22D)
+        22D = (22B, 22B)
+        "#};
+        assert_eq!(first_meeting(&cycles(input)), Some(7));
+        assert_eq!(brute_force(input, 100), Some(7));
+    }
+
+    #[test]
+    fn test_first_meeting_tail() {
+        // 11Z に着くのは 1 歩目だけ
+        let input = indoc! {r#"
+        L
+
+        11A = (11Z, 11Z)
+        11Z = (11B, 11B)
+        11B = (11B, 11B)
+        22A = (22Z, 22Z)
+        22Z = (22A, 22A)
+        "#};
+        assert_eq!(first_meeting(&cycles(input)), Some(1));
+    }
+
+    #[test]
+    fn test_first_meeting_never() {
+        // 奇数歩目と偶数歩目にしかゴールにいない
+        let input = indoc! {r#"
+        L
+
+        11A = (11Z, 11Z)
+        11Z = (11B, 11B)
+        11B = (11Z, 11Z)
+        22A = (22B, 22B)
+        22B = (22Z, 22Z)
+        22Z = (22B, 22B)
+        "#};
+        assert_eq!(first_meeting(&cycles(input)), None);
+        assert_eq!(brute_force(input, 100), None);
+    }
+
+    #[test]
+    fn test_compile() {
+        let (_, data) = parse_input(INPUT3).unwrap();
+        let network = Network::compile(&data).unwrap();
+
+        assert_eq!(network.len(), 8);
+        let a = network.id("11A").unwrap();
+        assert_eq!(network.name(a), "11A");
+        assert_eq!(network.name(network.next(a, Navigation::Left)), "11B");
+        assert_eq!(
+            network
+                .ghost_starts()
+                .into_iter()
+                .map(|id| network.name(id))
+                .collect::<Vec<_>>(),
+            ["11A", "22A"]
+        );
+
+        let (_, data) = parse_input("L\n\nAAA = (AAA, ZZZ)").unwrap();
+        assert!(Network::compile(&data).is_err());
+    }
+
+    #[test]
+    fn test_position() {
+        let (_, data) = parse_input(INPUT3).unwrap();
+        let network = Network::compile(&data).unwrap();
+
+        for start in 0..network.len() as u32 {
+            let mut node = start;
+            for step in 0..100u64 {
+                assert_eq!(network.position(start, step), node);
+                let nav = network.instructions()[step as usize % 2];
+                node = network.next(node, nav);
+            }
+        }
+
+        // 22A は 1 歩目から周期 3 で 22B, 22C, 22Z を回る
+        let start = network.id("22A").unwrap();
+        let at = |steps| network.name(network.position(start, steps));
+        assert_eq!(at(3_000_000_000_000), "22Z");
+        assert_eq!(at(u64::MAX), "22Z");
+    }
+
+    #[test]
+    fn test_analysis() {
+        let (_, data) = parse_input(INPUT3).unwrap();
+        let network = Network::compile(&data).unwrap();
+        let analysis = Analysis::new(&network);
+        let names = |nodes: &[u32]| nodes.iter().map(|&n| network.name(n)).collect::<Vec<_>>();
+
+        let mut components = analysis
+            .components
+            .iter()
+            .map(|c| names(c))
+            .collect::<Vec<_>>();
+        components.sort();
+        assert_eq!(
+            components,
+            [
+                vec!["11A"],
+                vec!["11B", "11Z"],
+                vec!["22A"],
+                vec!["22B", "22C", "22Z"],
+                vec!["XXX"],
+            ]
+        );
+        // XXX からはどこにも戻れないので、一番先に見つかる
+        assert_eq!(names(&analysis.components[0]), ["XXX"]);
+        // 成分をまたぐ辺は、後ろの成分から前の成分へ向かう
+        for node in 0..network.len() as u32 {
+            for next in network.edges(node) {
+                let (from, to) = (
+                    analysis.component[node as usize],
+                    analysis.component[next as usize],
+                );
+                assert!(
+                    to <= from,
+                    "{} -> {}",
+                    network.name(node),
+                    network.name(next)
+                );
+            }
+        }
+
+        let ghost = &analysis.ghosts[0];
+        assert_eq!(names(&ghost.reachable), ["11A", "11B", "11Z", "XXX"]);
+        assert_eq!(names(&ghost.loop_nodes), ["11B", "11Z"]);
+        assert_eq!(names(&ghost.loop_goals), ["11Z"]);
+
+        let ghost = &analysis.ghosts[1];
+        assert_eq!(names(&ghost.reachable), ["22A", "22B", "22C", "22Z", "XXX"]);
+        assert_eq!(names(&ghost.loop_nodes), ["22B", "22C", "22Z"]);
+        assert_eq!(names(&ghost.loop_goals), ["22Z"]);
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let (_, data) = parse_input(INPUT3).unwrap();
+        let network = Network::compile(&data).unwrap();
+        let dot = Analysis::new(&network).to_dot(&network);
+
+        assert!(dot.starts_with("digraph network {\n"));
+        assert!(dot.contains("\"11A\" [shape=box, color=green];"));
+        assert!(dot.contains("\"11Z\" [shape=doublecircle, color=red, xlabel=\"11A\"];"));
+        assert!(dot.contains("\"22B\" [xlabel=\"22A\"];"));
+        assert!(dot.contains("\"11A\" -> \"11B\" [label=\"L\"];"));
+        assert!(dot.contains("\"22B\" -> \"22C\" [label=\"LR\"];"));
+        assert_eq!(dot.matches("subgraph cluster_").count(), 2);
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt((2, 3), (3, 5)), Some((8, 15)));
+        assert_eq!(crt((1, 4), (3, 6)), Some((9, 12)));
+        assert_eq!(crt((0, 4), (1, 6)), None);
+        assert_eq!(crt((0, 1), (5, 7)), Some((5, 7)));
+    }
+
+    #[test]
+    fn test_gdc() {
+        let numbers = [8u32, 10, 15];
+        let mut v = numbers[0];
+        for n in numbers.iter().skip(1) {
+            v = lcm(v, *n);
+        }
+
+        assert_eq!(v, 120);
+    }
+}
@@ -1,7 +1,8 @@
 use std::fs;
 use std::io::{BufReader, Read};
 
-use day_09::{get_prediction, get_sequences, parse_input};
+use day_09::{check, parse_input, Sequence};
+use num::BigInt;
 
 fn main() -> anyhow::Result<()> {
     let mut r = BufReader::new(fs::File::open("day-09/data/input.txt")?);
@@ -16,10 +17,13 @@ fn main() -> anyhow::Result<()> {
 fn process(input: &str) -> anyhow::Result<String> {
     let (_, data) = parse_input(input).map_err(|e| anyhow::anyhow!("{:?}", e))?;
 
-    let mut total = 0;
+    for warning in check(&data.lines) {
+        println!("{}", warning);
+    }
+
+    let mut total = BigInt::from(0);
     for line in &data.lines {
-        let sequences = get_sequences(line);
-        let predict = get_prediction(&sequences);
+        let predict = Sequence::fit(line).at(line.len() as i64);
         println!("line:{:?} predict:{}", line, predict);
         total += predict;
     }
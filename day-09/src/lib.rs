@@ -1,62 +1,205 @@
+use std::fmt;
+
 use complete::newline;
 use nom::character::complete;
 use nom::character::complete::space1;
 use nom::multi::separated_list1;
 use nom::IResult;
+use num::{BigInt, BigRational, One, Zero};
 
 #[derive(Debug)]
 pub struct InputData {
-    pub lines: Vec<Vec<i32>>,
+    pub lines: Vec<Vec<i64>>,
 }
 
-pub fn get_sequences(line: &[i32]) -> Vec<Vec<i32>> {
-    fn seq(line: &[i32]) -> Vec<i32> {
-        let mut differs = Vec::new();
-        for i in 1..line.len() {
-            let (a, b) = (line[i - 1], line[i]);
-            differs.push(b - a);
-        }
-        differs
+/// 差分の表。最後の行はすべて 0（または空）になる
+pub fn get_sequences(line: &[i64]) -> Vec<Vec<BigInt>> {
+    fn seq(line: &[BigInt]) -> Vec<BigInt> {
+        line.windows(2).map(|w| &w[1] - &w[0]).collect()
     }
-    fn all_zero(line: &[i32]) -> bool {
-        line.iter().all(|&x| x == 0)
+    fn all_zero(line: &[BigInt]) -> bool {
+        line.iter().all(|x| x.is_zero())
     }
 
     let mut sequences = Vec::new();
-    sequences.push(line.to_vec());
+    sequences.push(line.iter().map(|&x| BigInt::from(x)).collect::<Vec<_>>());
 
-    let mut current = line;
     loop {
-        let next = seq(current);
-        if all_zero(&next) {
-            sequences.push(next);
+        let next = seq(sequences.last().expect("should have last"));
+        let done = all_zero(&next);
+        sequences.push(next);
+        if done {
             break;
         }
-        sequences.push(next);
-        current = &sequences.last().expect("should have last");
     }
 
     sequences
 }
 
-pub fn get_prediction(sequences: &[Vec<i32>]) -> i32 {
-    sequences.iter().map(|seq| seq.last().unwrap_or(&0)).sum()
+pub fn get_prediction(sequences: &[Vec<BigInt>]) -> BigInt {
+    sequences.iter().filter_map(|seq| seq.last()).sum()
 }
 
-pub fn get_prev_prediction(sequences: &[Vec<i32>]) -> i32 {
-    fn prev(i: usize, sequences: &[Vec<i32>]) -> i32 {
-        if i >= sequences.len() {
-            return 0;
+pub fn get_prev_prediction(sequences: &[Vec<BigInt>]) -> BigInt {
+    fn prev(i: usize, sequences: &[Vec<BigInt>]) -> BigInt {
+        match sequences.get(i).and_then(|seq| seq.first()) {
+            Some(first) => first - prev(i + 1, sequences),
+            None => BigInt::zero(),
         }
-        sequences[i][0] - prev(i + 1, sequences)
     }
 
     prev(0, sequences)
 }
 
+/// 数列にぴったり合う、次数が最小の多項式。
+/// 先頭を k = 0 として、ニュートンの前進差分 f(k) = Σ Δʲf(0) · C(k, j) で持つ。
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Sequence {
+    /// Δʲf(0)。末尾は 0 でない
+    differences: Vec<BigInt>,
+    /// 元の数列の長さ
+    len: usize,
+}
+
+/// 数列が多項式と言えるかどうか
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Fit {
+    /// 差分の表が、margin 個の 0 が並ぶ行で終わる
+    Polynomial {
+        degree: Option<usize>,
+        margin: usize,
+    },
+    /// 差分の表が、空の行まで 0 にならない
+    NotPolynomial,
+}
+
+impl Fit {
+    /// 最後の1個だけが 0 の行で終わっていて、裏付けが弱い
+    pub fn is_marginal(&self) -> bool {
+        matches!(self, Fit::Polynomial { margin: 1, .. })
+    }
+}
+
+impl fmt::Display for Fit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fit::Polynomial { degree: None, .. } => write!(f, "zero"),
+            Fit::Polynomial {
+                degree: Some(degree),
+                ..
+            } => write!(f, "degree {}", degree),
+            Fit::NotPolynomial => write!(f, "not polynomial within length"),
+        }
+    }
+}
+
+/// 予測を信用できない行
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Warning {
+    /// 0 始まりの行番号
+    pub line: usize,
+    pub fit: Fit,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "warning: line {}: {}", self.line + 1, self.fit)?;
+        if self.fit.is_marginal() {
+            write!(f, " (differences hit zero only at the last row)")?;
+        }
+        Ok(())
+    }
+}
+
+/// 多項式でない行と、裏付けが弱い行を返す
+pub fn check(lines: &[Vec<i64>]) -> Vec<Warning> {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(line, values)| Warning {
+            line,
+            fit: Sequence::fit(values).classify(),
+        })
+        .filter(|w| w.fit == Fit::NotPolynomial || w.fit.is_marginal())
+        .collect()
+}
+
+impl Sequence {
+    pub fn fit(line: &[i64]) -> Self {
+        let mut differences = get_sequences(line)
+            .into_iter()
+            .filter_map(|seq| seq.into_iter().next())
+            .collect::<Vec<_>>();
+        while differences.last().is_some_and(|d| d.is_zero()) {
+            differences.pop();
+        }
+        Sequence {
+            differences,
+            len: line.len(),
+        }
+    }
+
+    /// 0 になった差分の行の長さで、多項式かどうかを判定する
+    pub fn classify(&self) -> Fit {
+        match self.len - self.differences.len() {
+            0 => Fit::NotPolynomial,
+            margin => Fit::Polynomial {
+                degree: self.degree(),
+                margin,
+            },
+        }
+    }
+
+    /// 多項式の次数。すべて 0 の数列なら None
+    pub fn degree(&self) -> Option<usize> {
+        self.differences.len().checked_sub(1)
+    }
+
+    /// k 番目の値。負の k は先頭より前を表す
+    pub fn at(&self, k: impl Into<BigInt>) -> BigInt {
+        let k = k.into();
+
+        // C(k, j) = C(k, j - 1) · (k - j + 1) / j は割り切れる
+        let mut binomial = BigInt::one();
+        let mut value = BigInt::zero();
+        for (j, d) in self.differences.iter().enumerate() {
+            if j > 0 {
+                binomial = binomial * (&k - (j - 1)) / j;
+            }
+            value += d * &binomial;
+        }
+        value
+    }
+
+    /// f(k) = c₀ + c₁k + c₂k² + ... の係数 [c₀, c₁, c₂, ...]
+    pub fn coefficients(&self) -> Vec<BigRational> {
+        let mut coefficients = vec![BigRational::zero(); self.differences.len()];
+
+        // falling[i]: k(k-1)...(k-j+1) の kⁱ の係数
+        let mut falling = vec![BigInt::one()];
+        let mut factorial = BigInt::one();
+        for (j, d) in self.differences.iter().enumerate() {
+            if j > 0 {
+                let shift = BigInt::from(j - 1);
+                let mut next = vec![BigInt::zero(); j + 1];
+                for (i, c) in falling.iter().enumerate() {
+                    next[i + 1] += c;
+                    next[i] -= c * &shift;
+                }
+                falling = next;
+                factorial *= j;
+            }
+            for (i, c) in falling.iter().enumerate() {
+                coefficients[i] += BigRational::new(d * c, factorial.clone());
+            }
+        }
+        coefficients
+    }
+}
+
 pub fn parse_input(input: &str) -> IResult<&str, InputData> {
-    fn line(input: &str) -> IResult<&str, Vec<i32>> {
-        separated_list1(space1, complete::i32)(input)
+    fn line(input: &str) -> IResult<&str, Vec<i64>> {
+        separated_list1(space1, complete::i64)(input)
     }
 
     let (input, lines) = separated_list1(newline, line)(input)?;
@@ -66,6 +209,7 @@ pub fn parse_input(input: &str) -> IResult<&str, InputData> {
 #[cfg(test)]
 mod tests {
     use indoc::indoc;
+    use itertools::Itertools;
 
     use super::*;
 
@@ -84,45 +228,169 @@ mod tests {
 
     #[test]
     fn test_get_prediction() {
-        fn dump(sequences: &[Vec<i32>]) {
+        fn dump(sequences: &[Vec<BigInt>]) {
             for seq in sequences {
-                println!("{:?}", seq);
+                println!("{}", seq.iter().join(" "));
             }
         }
 
         let (_, data) = parse_input(INPUT).unwrap();
         let sequences = get_sequences(&data.lines[0]);
         dump(&sequences);
-        assert_eq!(get_prediction(&sequences), 18);
+        assert_eq!(get_prediction(&sequences), BigInt::from(18));
 
         let sequences = get_sequences(&data.lines[1]);
         dump(&sequences);
-        assert_eq!(get_prediction(&sequences), 28);
+        assert_eq!(get_prediction(&sequences), BigInt::from(28));
 
         let sequences = get_sequences(&data.lines[2]);
         dump(&sequences);
-        assert_eq!(get_prediction(&sequences), 68);
+        assert_eq!(get_prediction(&sequences), BigInt::from(68));
     }
 
     #[test]
     fn test_get_prev_prediction() {
-        fn dump(sequences: &[Vec<i32>]) {
+        fn dump(sequences: &[Vec<BigInt>]) {
             for seq in sequences {
-                println!("{:?}", seq);
+                println!("{}", seq.iter().join(" "));
             }
         }
 
         let (_, data) = parse_input(INPUT).unwrap();
         let sequences = get_sequences(&data.lines[0]);
         dump(&sequences);
-        assert_eq!(get_prev_prediction(&sequences), -3);
+        assert_eq!(get_prev_prediction(&sequences), BigInt::from(-3));
 
         let sequences = get_sequences(&data.lines[1]);
         dump(&sequences);
-        assert_eq!(get_prev_prediction(&sequences), 0);
+        assert_eq!(get_prev_prediction(&sequences), BigInt::from(0));
 
         let sequences = get_sequences(&data.lines[2]);
         dump(&sequences);
-        assert_eq!(get_prev_prediction(&sequences), 5);
+        assert_eq!(get_prev_prediction(&sequences), BigInt::from(5));
+    }
+
+    #[test]
+    fn test_sequence() {
+        let (_, data) = parse_input(INPUT).unwrap();
+        let models = data
+            .lines
+            .iter()
+            .map(|line| Sequence::fit(line))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            models.iter().map(|m| m.degree()).collect::<Vec<_>>(),
+            [Some(1), Some(2), Some(3)]
+        );
+        for (line, model) in data.lines.iter().zip(&models) {
+            for (k, &x) in line.iter().enumerate() {
+                assert_eq!(model.at(k as i64), BigInt::from(x));
+            }
+        }
+        assert_eq!(models[0].at(6), BigInt::from(18));
+        assert_eq!(models[1].at(6), BigInt::from(28));
+        assert_eq!(models[2].at(6), BigInt::from(68));
+        assert_eq!(models[0].at(-1), BigInt::from(-3));
+        assert_eq!(models[1].at(-1), BigInt::from(0));
+        assert_eq!(models[2].at(-1), BigInt::from(5));
+
+        assert_eq!(Sequence::fit(&[0, 0, 0]).degree(), None);
+        assert_eq!(Sequence::fit(&[0, 0, 0]).at(100), BigInt::zero());
+        assert_eq!(Sequence::fit(&[7]).degree(), Some(0));
+    }
+
+    #[test]
+    fn test_coefficients() {
+        let ratio = |n: i64, d: i64| BigRational::new(n.into(), d.into());
+
+        // 3k
+        assert_eq!(
+            Sequence::fit(&[0, 3, 6, 9]).coefficients(),
+            [ratio(0, 1), ratio(3, 1)]
+        );
+        // (k + 1)(k + 2) / 2 = 1 + 3k/2 + k²/2
+        assert_eq!(
+            Sequence::fit(&[1, 3, 6, 10, 15, 21]).coefficients(),
+            [ratio(1, 1), ratio(3, 2), ratio(1, 2)]
+        );
+        // 3 次式は、係数から計算しても同じ値になる
+        let model = Sequence::fit(&[10, 13, 16, 21, 30, 45]);
+        let coefficients = model.coefficients();
+        for k in -5..10i64 {
+            let value = coefficients
+                .iter()
+                .rev()
+                .fold(BigRational::zero(), |acc, c| acc * ratio(k, 1) + c);
+            assert_eq!(value, BigRational::from(model.at(k)));
+        }
+    }
+
+    #[test]
+    fn test_large() {
+        // k³ は i32 はもちろん i64 にも収まらない
+        let line = (0..8i64).map(|k| k * k * k).collect::<Vec<_>>();
+        let model = Sequence::fit(&line);
+        let k = BigInt::from(10i64.pow(12));
+        assert_eq!(model.at(k.clone()), &k * &k * &k);
+
+        let line = [i64::MAX, i64::MIN, i64::MAX];
+        let sequences = get_sequences(&line);
+        assert_eq!(get_prediction(&sequences), Sequence::fit(&line).at(3));
+    }
+
+    #[test]
+    fn test_classify() {
+        let (_, data) = parse_input(INPUT).unwrap();
+        let fits = data
+            .lines
+            .iter()
+            .map(|line| Sequence::fit(line).classify())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fits,
+            [
+                Fit::Polynomial {
+                    degree: Some(1),
+                    margin: 4
+                },
+                Fit::Polynomial {
+                    degree: Some(2),
+                    margin: 3
+                },
+                Fit::Polynomial {
+                    degree: Some(3),
+                    margin: 2
+                },
+            ]
+        );
+        assert!(check(&data.lines).is_empty());
+
+        assert_eq!(
+            Sequence::fit(&[0, 0]).classify(),
+            Fit::Polynomial {
+                degree: None,
+                margin: 2
+            }
+        );
+        // 2ᵏ は何度差分をとっても 0 にならない
+        assert_eq!(Sequence::fit(&[1, 2, 4, 8]).classify(), Fit::NotPolynomial);
+        // k² の最後の差分だけが 0
+        let fit = Sequence::fit(&[0, 1, 4, 9]).classify();
+        assert!(fit.is_marginal());
+    }
+
+    #[test]
+    fn test_check() {
+        let lines = vec![vec![0, 3, 6, 9], vec![1, 2, 4, 8], vec![0, 1, 4, 9]];
+        let warnings = check(&lines);
+
+        assert_eq!(
+            warnings.iter().map(|w| w.to_string()).collect::<Vec<_>>(),
+            [
+                "warning: line 2: not polynomial within length",
+                "warning: line 3: degree 2 (differences hit zero only at the last row)",
+            ]
+        );
     }
 }
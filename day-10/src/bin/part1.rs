@@ -1,7 +1,7 @@
 use std::fs;
 use std::io::{BufReader, Read};
 
-use day_10::{parse_input, search_path};
+use day_10::{parse_input, trace_loop};
 
 fn main() -> anyhow::Result<()> {
     let mut r = BufReader::new(fs::File::open("day-10/data/input.txt")?);
@@ -16,14 +16,15 @@ fn main() -> anyhow::Result<()> {
 fn process(input: &str) -> anyhow::Result<String> {
     let data = parse_input(input)?;
 
-    let paths = search_path(&data);
-    for p in &paths {
-        println!("path.len: {:?}", p.len());
-    }
+    let l = trace_loop(&data)?;
+    println!(
+        "S: {}, loop.len: {}, vertices: {}",
+        l.start_shape,
+        l.len,
+        l.vertices.len()
+    );
 
-    // 一番長くでるーぷしている物のステップ数を取得する。
-    let far = paths.iter().map(|p| p.len()).max().expect("no max") / 2;
-    Ok(far.to_string())
+    Ok(l.farthest().to_string())
 }
 
 #[cfg(test)]
@@ -1,7 +1,7 @@
 use std::fs;
 use std::io::{BufReader, Read};
 
-use day_10::{dump_map, is_outside, parse_input, search_area, search_path};
+use day_10::{classify, count_interior, parse_input, render, trace_loop, Interior};
 
 fn main() -> anyhow::Result<()> {
     let mut r = BufReader::new(fs::File::open("day-10/data/input.txt")?);
@@ -16,24 +16,23 @@ fn main() -> anyhow::Result<()> {
 
 fn process(input: &str) -> anyhow::Result<String> {
     let data = parse_input(input)?;
+    let l = trace_loop(&data)?;
 
-    let mut count_of_tiles = 0;
-    for path in &search_path(&data) {
-        println!("----------");
+    println!("{}", render(&data, &l, &classify(&data, &l, 3)?));
 
-        // 進行方向に対して、右側のエリアを取得
-        let area = search_area(&data, path);
-        dump_map(&data, &area, path);
-
-        // 外側と判断すればカウントしない。
-        if is_outside(&data, &area) {
-            continue;
-        }
-
-        count_of_tiles = area.len();
+    // 数え方ごとの結果が一致することを確かめる
+    let counts = Interior::ALL
+        .iter()
+        .map(|&method| count_interior(&data, &l, method))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    for (method, count) in Interior::ALL.iter().zip(&counts) {
+        println!("{:?}: {}", method, count);
+    }
+    if counts.iter().any(|&c| c != counts[0]) {
+        anyhow::bail!("interior counts disagree: {:?}", counts);
     }
 
-    Ok(count_of_tiles.to_string())
+    Ok(counts[0].to_string())
 }
 
 #[cfg(test)]
@@ -7,38 +7,133 @@ use nom::InputIter;
 pub type Path = Vec<IVec2>;
 pub type Area = HashSet<IVec2>;
 
-pub fn search_path(data: &InputData) -> Vec<Path> {
-    fn s(data: &InputData, results: &mut Vec<Path>, path: &Path) {
-        let current = path.last().expect("no last");
+const NORTH: IVec2 = IVec2::new(0, -1);
+const SOUTH: IVec2 = IVec2::new(0, 1);
+const WEST: IVec2 = IVec2::new(-1, 0);
+const EAST: IVec2 = IVec2::new(1, 0);
+
+/// パイプがつながっている2方向
+fn connections(c: u8) -> Option<[IVec2; 2]> {
+    match c {
+        b'|' => Some([NORTH, SOUTH]),
+        b'-' => Some([WEST, EAST]),
+        b'L' => Some([NORTH, EAST]),
+        b'J' => Some([NORTH, WEST]),
+        b'7' => Some([SOUTH, WEST]),
+        b'F' => Some([SOUTH, EAST]),
+        _ => None,
+    }
+}
 
-        let prev = if path.len() >= 2 {
-            path.get(path.len() - 2)
-        } else {
-            None
-        };
+/// S の下にあるパイプを、つながっている隣から決める。
+/// 3つ以上の隣がつながっているときは、ループが閉じる形を選ぶ。
+pub fn infer_start(data: &InputData) -> anyhow::Result<char> {
+    let linked = [NORTH, SOUTH, WEST, EAST]
+        .into_iter()
+        .filter(|&d| {
+            data.tile(&(data.start + d))
+                .and_then(connections)
+                .is_some_and(|c| c.contains(&-d))
+        })
+        .collect::<Vec<_>>();
+
+    let candidates = "|-LJ7F"
+        .chars()
+        .filter(|&c| connections(c as u8).is_some_and(|c| c.iter().all(|d| linked.contains(d))))
+        .collect::<Vec<_>>();
+
+    match candidates[..] {
+        [] => anyhow::bail!("S must connect at least 2 pipes: {:?}", linked),
+        [c] => Ok(c),
+        _ => candidates
+            .into_iter()
+            .find(|&c| walk(data, c).is_ok())
+            .ok_or_else(|| anyhow::anyhow!("no loop passes through S: {:?}", linked)),
+    }
+}
 
-        for (next_s, next) in data.get_pos_connected(current) {
-            if let Some(prev) = prev {
-                if next == *prev {
-                    // すでに通ってきた道は通らない
-                    continue;
-                }
+/// S を通って一周するループ
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Loop {
+    pub start: IVec2,
+    /// S の下にあるパイプ
+    pub start_shape: char,
+    /// S から進む順に並べた角の位置
+    pub vertices: Vec<IVec2>,
+    /// ループのタイル数
+    pub len: usize,
+}
+
+impl Loop {
+    /// S から一番遠いタイルまでの歩数
+    pub fn farthest(&self) -> usize {
+        self.len / 2
+    }
+
+    /// S から進む順のすべてのタイル。最後は S に戻る
+    pub fn path(&self) -> Path {
+        let mut path = vec![self.start];
+        let corners = self.vertices.iter().filter(|&&v| v != self.start);
+        for &to in corners.chain([&self.start]) {
+            let mut pos = *path.last().expect("no last");
+            let d = (to - pos).signum();
+            while pos != to {
+                pos += d;
+                path.push(pos);
             }
+        }
+        path
+    }
+}
 
-            let mut new_path = path.clone();
-            new_path.push(next);
+/// S から片方向に一周してループを求める
+pub fn trace_loop(data: &InputData) -> anyhow::Result<Loop> {
+    walk(data, infer_start(data)?)
+}
 
-            if next_s == "S" {
-                results.push(new_path);
-            } else {
-                s(data, results, &new_path);
+/// S の下が start_shape だとして一周する
+fn walk(data: &InputData, start_shape: char) -> anyhow::Result<Loop> {
+    let [mut dir, _] = connections(start_shape as u8).expect("start shape is a pipe");
+
+    let mut vertices = Vec::new();
+    if !"|-".contains(start_shape) {
+        vertices.push(data.start);
+    }
+
+    let mut pos = data.start;
+    let mut len = 0;
+    loop {
+        pos += dir;
+        len += 1;
+        if pos == data.start {
+            // S のもう一方の腕から戻ってこなければ、start_shape では閉じない
+            let arms = connections(start_shape as u8).expect("start shape is a pipe");
+            if !arms.contains(&-dir) {
+                anyhow::bail!("loop does not return to S through {}", start_shape);
             }
+            break;
+        }
+
+        let c = data.tile(&pos).unwrap_or(b'.');
+        let [a, b] = connections(c).ok_or_else(|| anyhow::anyhow!("loop is broken at {}", pos))?;
+        dir = if a == -dir {
+            b
+        } else if b == -dir {
+            a
+        } else {
+            anyhow::bail!("loop is broken at {}", pos);
+        };
+        if a != -b {
+            vertices.push(pos);
         }
     }
 
-    let mut path_results = Vec::new();
-    s(data, &mut path_results, &vec![data.start]);
-    path_results
+    Ok(Loop {
+        start: data.start,
+        start_shape,
+        vertices,
+        len,
+    })
 }
 
 pub fn search_area(data: &InputData, path: &Path) -> HashSet<IVec2> {
@@ -110,27 +205,258 @@ pub fn search_area(data: &InputData, path: &Path) -> HashSet<IVec2> {
     area
 }
 
-fn fill_area(data: &InputData, area: &mut HashSet<IVec2>, pos: &IVec2, path_set: &HashSet<IVec2>) {
-    if !area.insert(*pos) {
-        return;
+/// 内側のタイルの数え方
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Interior {
+    /// ループの右側を塗りつぶし、外側に触れなかった方を採用する
+    FloodFill,
+    /// 角の座標から靴紐公式で面積を求め、ピックの定理で内部の格子点を数える
+    Shoelace,
+    /// 各行を左から走査し、北につながるパイプをまたいだ回数の偶奇で判定する
+    Scanline,
+    /// 地図を拡大してパイプの隙間を通れるようにし、外周から塗りつぶす
+    Upsampled(usize),
+}
+
+impl Interior {
+    pub const ALL: [Interior; 5] = [
+        Interior::FloodFill,
+        Interior::Shoelace,
+        Interior::Scanline,
+        Interior::Upsampled(2),
+        Interior::Upsampled(3),
+    ];
+}
+
+/// ループに囲まれたタイルの数
+pub fn count_interior(data: &InputData, l: &Loop, method: Interior) -> anyhow::Result<usize> {
+    let count = match method {
+        Interior::FloodFill => flood_fill_area(data, l).len(),
+        Interior::Shoelace => {
+            let n = l.vertices.len();
+            let doubled_area = (0..n)
+                .map(|i| {
+                    let (a, b) = (l.vertices[i], l.vertices[(i + 1) % n]);
+                    a.x as i64 * b.y as i64 - b.x as i64 * a.y as i64
+                })
+                .sum::<i64>()
+                .abs();
+            // A = I + B / 2 - 1
+            ((doubled_area - l.len as i64) / 2 + 1) as usize
+        }
+        Interior::Scanline => {
+            let width = data.x_range.end as usize;
+            let mut on_loop = vec![false; width * data.y_range.end as usize];
+            for pos in l.path() {
+                on_loop[pos.y as usize * width + pos.x as usize] = true;
+            }
+
+            let mut count = 0;
+            for y in data.y_range.clone() {
+                let mut inside = false;
+                for x in data.x_range.clone() {
+                    let pos = IVec2::new(x, y);
+                    if !on_loop[y as usize * width + x as usize] {
+                        count += inside as usize;
+                        continue;
+                    }
+                    let c = if pos == l.start {
+                        l.start_shape as u8
+                    } else {
+                        data.tile(&pos).expect("Illegal position")
+                    };
+                    if matches!(c, b'|' | b'L' | b'J') {
+                        inside = !inside;
+                    }
+                }
+            }
+            count
+        }
+        Interior::Upsampled(scale) => classify(data, l, scale)?.count(Tile::Inside),
+    };
+    Ok(count)
+}
+
+/// タイルの分類
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Tile {
+    Inside,
+    Outside,
+    Loop,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TileMap {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<Tile>,
+}
+
+impl TileMap {
+    pub fn get(&self, pos: &IVec2) -> Tile {
+        self.tiles[pos.y as usize * self.width + pos.x as usize]
     }
 
-    for d in [
-        IVec2::new(-1, 0),
-        IVec2::new(1, 0),
-        IVec2::new(0, -1),
-        IVec2::new(0, 1),
-    ] {
-        let next = *pos + d;
-        if !data.in_range(&next) {
-            continue;
+    pub fn count(&self, tile: Tile) -> usize {
+        self.tiles.iter().filter(|&&t| t == tile).count()
+    }
+}
+
+/// 地図を scale 倍に拡大して、すべてのタイルを分類する。
+/// ループのパイプは、拡大したマスの中心とつながる方向の腕を塞ぐので、
+/// 並んだパイプの間はすり抜けられる。
+pub fn classify(data: &InputData, l: &Loop, scale: usize) -> anyhow::Result<TileMap> {
+    if scale < 2 {
+        anyhow::bail!("scale must be at least 2: {}", scale);
+    }
+
+    let (width, height) = (data.x_range.end as usize, data.y_range.end as usize);
+    let (sub_width, sub_height) = (width * scale, height * scale);
+    let center = |pos: IVec2| pos * scale as i32 + IVec2::splat(scale as i32 / 2);
+    let index = |p: IVec2| p.y as usize * sub_width + p.x as usize;
+    let in_sub = |p: IVec2| {
+        p.x >= 0 && p.y >= 0 && (p.x as usize) < sub_width && (p.y as usize) < sub_height
+    };
+
+    let mut on_loop = vec![false; width * height];
+    let mut blocked = vec![false; sub_width * sub_height];
+    for pos in l.path() {
+        on_loop[pos.y as usize * width + pos.x as usize] = true;
+
+        let c = if pos == l.start {
+            l.start_shape as u8
+        } else {
+            data.tile(&pos).expect("Illegal position")
         };
+        let center = center(pos);
+        blocked[index(center)] = true;
+        for d in connections(c).expect("loop tile is a pipe") {
+            if in_sub(center + d) {
+                blocked[index(center + d)] = true;
+            }
+        }
+    }
+
+    // 外周の空いているマスから塗りつぶす
+    let mut outside = vec![false; sub_width * sub_height];
+    let mut stack = (0..sub_width as i32)
+        .flat_map(|x| [IVec2::new(x, 0), IVec2::new(x, sub_height as i32 - 1)])
+        .chain(
+            (0..sub_height as i32)
+                .flat_map(|y| [IVec2::new(0, y), IVec2::new(sub_width as i32 - 1, y)]),
+        )
+        .filter(|&p| !blocked[index(p)])
+        .collect::<Vec<_>>();
+    while let Some(p) = stack.pop() {
+        if outside[index(p)] {
+            continue;
+        }
+        outside[index(p)] = true;
+        for d in [NORTH, SOUTH, WEST, EAST] {
+            let next = p + d;
+            if in_sub(next) && !blocked[index(next)] && !outside[index(next)] {
+                stack.push(next);
+            }
+        }
+    }
+
+    let tiles = data
+        .y_range
+        .clone()
+        .flat_map(|y| data.x_range.clone().map(move |x| IVec2::new(x, y)))
+        .map(|pos| {
+            if on_loop[pos.y as usize * width + pos.x as usize] {
+                Tile::Loop
+            } else if outside[index(center(pos))] {
+                Tile::Outside
+            } else {
+                Tile::Inside
+            }
+        })
+        .collect();
+
+    Ok(TileMap {
+        width,
+        height,
+        tiles,
+    })
+}
+
+/// 分類した地図を罫線で描く。内側は █、外側とループ以外のパイプは空白にする
+pub fn render(data: &InputData, l: &Loop, map: &TileMap) -> String {
+    let mut lines = Vec::new();
+    for y in data.y_range.clone() {
+        let line = data
+            .x_range
+            .clone()
+            .map(|x| {
+                let pos = IVec2::new(x, y);
+                match map.get(&pos) {
+                    Tile::Inside => '█',
+                    Tile::Outside => ' ',
+                    Tile::Loop => {
+                        let c = if pos == l.start {
+                            l.start_shape as u8
+                        } else {
+                            data.tile(&pos).expect("Illegal position")
+                        };
+                        match c {
+                            b'|' => '│',
+                            b'-' => '─',
+                            b'L' => '└',
+                            b'J' => '┘',
+                            b'7' => '┐',
+                            b'F' => '┌',
+                            _ => '?',
+                        }
+                    }
+                }
+            })
+            .collect::<String>();
+        lines.push(line.trim_end().to_string());
+    }
+    lines.join("\n")
+}
 
-        if path_set.contains(&next) {
+/// ループの両方向について右側を塗りつぶし、外側に触れなかった方を返す。
+/// ループが地図の端に沿っていると外側は空になるので、大きい方を選ぶ。
+pub fn flood_fill_area(data: &InputData, l: &Loop) -> Area {
+    let path = l.path();
+    let reversed = path.iter().rev().copied().collect::<Vec<_>>();
+
+    [path, reversed]
+        .iter()
+        .map(|path| search_area(data, path))
+        .filter(|area| !is_outside(data, area))
+        .max_by_key(|area| area.len())
+        .unwrap_or_default()
+}
+
+/// 再帰すると広い領域でスタックが溢れるので、明示的なスタックで塗りつぶす
+fn fill_area(data: &InputData, area: &mut HashSet<IVec2>, pos: &IVec2, path_set: &HashSet<IVec2>) {
+    let mut stack = vec![*pos];
+    while let Some(pos) = stack.pop() {
+        if !area.insert(pos) {
             continue;
         }
 
-        fill_area(data, area, &next, path_set);
+        for d in [
+            IVec2::new(-1, 0),
+            IVec2::new(1, 0),
+            IVec2::new(0, -1),
+            IVec2::new(0, 1),
+        ] {
+            let next = pos + d;
+            if !data.in_range(&next) {
+                continue;
+            };
+
+            if path_set.contains(&next) || area.contains(&next) {
+                continue;
+            }
+
+            stack.push(next);
+        }
     }
 }
 
@@ -144,24 +470,6 @@ pub fn is_outside(data: &InputData, area: &Area) -> bool {
     })
 }
 
-pub fn dump_map(data: &InputData, area: &Area, path: &Path) {
-    let path_set = path.iter().cloned().collect::<HashSet<_>>();
-
-    for y in data.y_range.clone() {
-        for x in data.x_range.clone() {
-            let pos = IVec2::new(x, y);
-            if area.contains(&pos) {
-                print!("#");
-            } else if path_set.contains(&pos) {
-                print!("{}", data.get_s(&pos).expect("Illegal position"));
-            } else {
-                print!(" ");
-            }
-        }
-        println!();
-    }
-}
-
 #[derive(Debug)]
 pub struct InputData<'a> {
     pub grid: Vec<&'a str>,
@@ -171,7 +479,7 @@ pub struct InputData<'a> {
     pub y_range: Range<i32>,
 }
 
-pub fn parse_input(input: &str) -> anyhow::Result<InputData> {
+pub fn parse_input(input: &str) -> anyhow::Result<InputData<'_>> {
     let grid = input.lines().collect::<Vec<_>>();
 
     let mut start = None;
@@ -182,11 +490,12 @@ pub fn parse_input(input: &str) -> anyhow::Result<InputData> {
         }
     }
 
-    Ok(InputData::new(grid, start.expect("no start")))
+    let start = start.ok_or_else(|| anyhow::anyhow!("no start"))?;
+    Ok(InputData::new(grid, start))
 }
 
 impl InputData<'_> {
-    fn new(grid: Vec<&str>, start: IVec2) -> InputData {
+    fn new(grid: Vec<&str>, start: IVec2) -> InputData<'_> {
         let x_range = 0..grid[0].len() as i32;
         let y_range = 0..grid.len() as i32;
 
@@ -209,18 +518,25 @@ impl InputData<'_> {
         Some(s)
     }
 
+    pub fn tile(&self, pos: &IVec2) -> Option<u8> {
+        if !self.in_range(pos) {
+            return None;
+        }
+        Some(self.grid[pos.y as usize].as_bytes()[pos.x as usize])
+    }
+
     pub fn in_range(&self, pos: &IVec2) -> bool {
         self.x_range.contains(&pos.x) && self.y_range.contains(&pos.y)
     }
 
-    /// | is a vertical pipe connecting north and south.
-    /// - is a horizontal pipe connecting east and west.
-    /// L is a 90-degree bend connecting north and east.
-    /// J is a 90-degree bend connecting north and west.
-    /// 7 is a 90-degree bend connecting south and west.
-    /// F is a 90-degree bend connecting south and east.
-    /// . is ground; there is no pipe in this tile.
-    /// S is the starting position of the animal; there is a pipe on this
+    /// * `|` is a vertical pipe connecting north and south.
+    /// * `-` is a horizontal pipe connecting east and west.
+    /// * `L` is a 90-degree bend connecting north and east.
+    /// * `J` is a 90-degree bend connecting north and west.
+    /// * `7` is a 90-degree bend connecting south and west.
+    /// * `F` is a 90-degree bend connecting south and east.
+    /// * `.` is ground; there is no pipe in this tile.
+    /// * `S` is the starting position of the animal; there is a pipe on this
     fn get_pos_as_possible(&self, pos: &IVec2) -> Vec<IVec2> {
         let Some(s) = self.get_s(pos) else {
             return vec![];
@@ -286,6 +602,44 @@ mod tests {
     LJ...
     "#};
 
+    const INPUT3: &str = indoc! {r#"
+    ...........
+    .S-------7.
+    .|F-----7|.
+    .||.....||.
+    .||.....||.
+    .|L-7.F-J|.
+    .|..|.|..|.
+    .L--J.L--J.
+    ...........
+    "#};
+
+    const INPUT4: &str = indoc! {r#"
+    .F----7F7F7F7F-7....
+    .|F--7||||||||FJ....
+    .||.FJ||||||||L7....
+    FJL7L7LJLJ||LJ.L-7..
+    L--J.L7...LJS7F-7L7.
+    ....F-J..F7FJ|L7L7L7
+    ....L7.F7||L7|.L7L7|
+    .....|FJLJ|FJ|F7|.LJ
+    ....FJL-7.||.||||...
+    ....L---J.LJ.LJLJ...
+    "#};
+
+    const INPUT5: &str = indoc! {r#"
+    FF7FSF7F7F7F7F7F---7
+    L|LJ||||||||||||F--J
+    FL-7LJLJ||||||LJL-77
+    F--JF--7||LJLJ7F7FJ-
+    L---JF-JLJ.||-FJLJJ7
+    |F|F-JF---7F7-L7L|7|
+    |FFJF7L7F-JF7|JL---7
+    7-L-JL7||F7|L7F-7F7|
+    L.L7LFJ|||||FJL7||LJ
+    L7JLJL-JLJLJL--JLJ.L
+    "#};
+
     #[test]
     fn test_parse_input() {
         let data = parse_input(INPUT).unwrap();
@@ -329,4 +683,183 @@ mod tests {
             ("-", IVec2::new(2, 1)),
         ]);
     }
+
+    #[test]
+    fn test_infer_start() {
+        let data = parse_input(INPUT).unwrap();
+        assert_eq!(infer_start(&data).unwrap(), 'F');
+
+        let data = parse_input(INPUT2).unwrap();
+        assert_eq!(infer_start(&data).unwrap(), 'F');
+
+        let data = parse_input("-S-\n...").unwrap();
+        assert_eq!(infer_start(&data).unwrap(), '-');
+
+        // つながる隣が3つ以上なら、ループが閉じる形を選ぶ
+        let data = parse_input(".|...\n.S-7.\n.|.|.\n.L-J.").unwrap();
+        assert_eq!(infer_start(&data).unwrap(), 'F');
+        assert_eq!(trace_loop(&data).unwrap().len, 8);
+        let data = parse_input(".|...\n-S-7.\n.|.|.\n.L-J.").unwrap();
+        assert_eq!(infer_start(&data).unwrap(), 'F');
+
+        // どの形でもループが閉じない
+        let data = parse_input("-S-\n.|.").unwrap();
+        assert!(infer_start(&data).is_err());
+        let data = parse_input(".S.\n...").unwrap();
+        assert!(infer_start(&data).is_err());
+    }
+
+    #[test]
+    fn test_trace_loop() {
+        let data = parse_input(INPUT).unwrap();
+        let l = trace_loop(&data).unwrap();
+        assert_eq!(l.len, 8);
+        assert_eq!(l.farthest(), 4);
+        #[rustfmt::skip] assert_eq!(l.vertices, vec![
+            IVec2::new(1, 1), IVec2::new(1, 3), IVec2::new(3, 3), IVec2::new(3, 1),
+        ]);
+        #[rustfmt::skip] assert_eq!(l.path(), vec![
+            IVec2::new(1, 1), IVec2::new(1, 2), IVec2::new(1, 3), IVec2::new(2, 3),
+            IVec2::new(3, 3), IVec2::new(3, 2), IVec2::new(3, 1), IVec2::new(2, 1),
+            IVec2::new(1, 1),
+        ]);
+
+        let data = parse_input(INPUT2).unwrap();
+        let l = trace_loop(&data).unwrap();
+        assert_eq!(l.len, 16);
+        assert_eq!(l.farthest(), 8);
+        assert_eq!(l.path().len(), 17);
+    }
+
+    /// n x n のすべてのタイルを通る蛇行したループ
+    fn serpentine(n: usize) -> String {
+        let mut grid = vec![vec![b'.'; n]; n];
+        grid[0].fill(b'-');
+        grid[0][0] = b'S';
+        grid[0][n - 1] = b'7';
+        for (y, row) in grid.iter_mut().enumerate().skip(1) {
+            row[0] = b'|';
+            row[1..].fill(b'-');
+            if y % 2 == 1 {
+                row[n - 1] = b'J';
+                row[1] = b'F';
+            } else {
+                row[1] = b'L';
+                row[n - 1] = b'7';
+            }
+        }
+        grid[n - 1][0] = b'L';
+        grid[n - 1][1] = b'-';
+        grid.into_iter()
+            .map(|row| String::from_utf8(row).unwrap())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_trace_loop_large() {
+        let n = 1000;
+        let input = serpentine(n);
+        let data = parse_input(&input).unwrap();
+
+        let l = trace_loop(&data).unwrap();
+        assert_eq!(l.start_shape, 'F');
+        assert_eq!(l.len, n * n);
+        assert_eq!(l.farthest(), n * n / 2);
+        // S と右上、それ以外の各行の両端
+        assert_eq!(l.vertices.len(), 2 + 2 * (n - 1));
+    }
+
+    #[test]
+    fn test_count_interior() {
+        for (input, expected) in [
+            (INPUT, 1),
+            (INPUT2, 1),
+            (INPUT3, 4),
+            (INPUT4, 8),
+            (INPUT5, 10),
+        ] {
+            let data = parse_input(input).unwrap();
+            let l = trace_loop(&data).unwrap();
+            for method in Interior::ALL {
+                assert_eq!(
+                    count_interior(&data, &l, method).unwrap(),
+                    expected,
+                    "{:?}",
+                    method
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_interior_large() {
+        // 蛇行したループは、隙間なく折り返すので内側がない
+        let input = serpentine(200);
+        let data = parse_input(&input).unwrap();
+        let l = trace_loop(&data).unwrap();
+        assert_eq!(count_interior(&data, &l, Interior::Shoelace).unwrap(), 0);
+        assert_eq!(count_interior(&data, &l, Interior::Scanline).unwrap(), 0);
+
+        // 外周だけのループ
+        let n = 500;
+        let mut lines = vec![format!("S{}7", "-".repeat(n - 2))];
+        lines.extend((2..n).map(|_| format!("|{}|", ".".repeat(n - 2))));
+        lines.push(format!("L{}J", "-".repeat(n - 2)));
+        let input = lines.join("\n");
+        let data = parse_input(&input).unwrap();
+        let l = trace_loop(&data).unwrap();
+        assert_eq!(
+            count_interior(&data, &l, Interior::Shoelace).unwrap(),
+            (n - 2) * (n - 2)
+        );
+        assert_eq!(
+            count_interior(&data, &l, Interior::Scanline).unwrap(),
+            (n - 2) * (n - 2)
+        );
+        // 塗りつぶしが深くなってもスタックが溢れない
+        assert_eq!(
+            count_interior(&data, &l, Interior::FloodFill).unwrap(),
+            (n - 2) * (n - 2)
+        );
+    }
+
+    #[test]
+    fn test_classify() {
+        let data = parse_input(INPUT3).unwrap();
+        let l = trace_loop(&data).unwrap();
+
+        for scale in [2, 3] {
+            let map = classify(&data, &l, scale).unwrap();
+            assert_eq!(map.count(Tile::Loop), l.len);
+            assert_eq!(map.count(Tile::Inside), 4);
+            assert_eq!(map.get(&IVec2::new(2, 6)), Tile::Inside);
+            // パイプの隙間から入り込める
+            assert_eq!(map.get(&IVec2::new(5, 3)), Tile::Outside);
+        }
+
+        // 拡大しないとパイプの隙間を通れない
+        assert!(classify(&data, &l, 1).is_err());
+        assert!(count_interior(&data, &l, Interior::Upsampled(0)).is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let data = parse_input(INPUT3).unwrap();
+        let l = trace_loop(&data).unwrap();
+        let map = classify(&data, &l, 3).unwrap();
+
+        let expected = [
+            "",
+            " ┌───────┐",
+            " │┌─────┐│",
+            " ││     ││",
+            " ││     ││",
+            " │└─┐ ┌─┘│",
+            " │██│ │██│",
+            " └──┘ └──┘",
+            "",
+        ];
+        assert_eq!(render(&data, &l, &map), expected.join("\n"));
+    }
 }
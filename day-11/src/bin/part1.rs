@@ -1,7 +1,7 @@
 use std::fs;
 use std::io::{BufReader, Read};
 
-use day_11::{make_expanded_stars, make_pairs, measure_length, parse_input};
+use day_11::{distance_sum, parse_input};
 
 fn main() -> anyhow::Result<()> {
     let mut r = BufReader::new(fs::File::open("day-11/data/input.txt")?);
@@ -16,17 +16,8 @@ fn main() -> anyhow::Result<()> {
 
 fn process(input: &str) -> anyhow::Result<String> {
     let data = parse_input(input);
-    let stars = make_expanded_stars(&data, 2);
-    let pairs = make_pairs(stars.len());
-
-    let mut total = 0;
-    for (i, j) in pairs {
-        let distance = measure_length(&stars[i].pos, &stars[j].pos);
-        println!("{} -> {}: {}", stars[i].id, stars[j].id, distance);
-        total += distance;
-    }
-
-    Ok(total.to_string())
+    let sum = distance_sum(&data);
+    Ok(sum.at(2).to_string())
 }
 
 #[cfg(test)]
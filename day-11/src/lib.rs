@@ -1,7 +1,6 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use glam::I64Vec2;
-use itertools::Itertools;
 
 pub type Int = i64;
 pub type Vec2 = I64Vec2;
@@ -43,60 +42,221 @@ pub fn parse_input(input: &str) -> InputData {
     InputData { stars }
 }
 
-fn make_mutation(
-    data: &InputData,
-    empty_size: Int,
-    get_pos: fn(&Star) -> Int,
-) -> HashMap<i32, Int> {
-    let group = data.stars.iter().into_group_map_by(|star| get_pos(star));
-    let mut migrates = HashMap::new();
-    let mut prev = -1;
-    for v in group.keys().sorted() {
-        let margin = v - prev - 1;
-
-        if margin > 0 {
-            let margin = (empty_size * margin) - 1;
-            for star in &data.stars {
-                if get_pos(star) >= *v {
-                    migrates
-                        .entry(star.id)
-                        .and_modify(|e| *e += margin)
-                        .or_insert(margin);
-                }
-            }
-        }
+/// empty[v]: v より前にある、星のない行（列）の数
+pub fn empty_before(coords: impl Iterator<Item = Int>) -> Vec<Int> {
+    let coords = coords.collect::<Vec<_>>();
+    let size = coords.iter().max().map_or(0, |&max| max as usize + 1);
 
-        prev = *v;
+    let mut occupied = vec![false; size];
+    for &v in &coords {
+        occupied[v as usize] = true;
     }
 
-    migrates
+    let mut empty = Vec::with_capacity(size);
+    let mut count = 0;
+    for occupied in occupied {
+        empty.push(count);
+        if !occupied {
+            count += 1;
+        }
+    }
+    empty
 }
 
 pub fn make_expanded_stars(data: &InputData, empty_size: Int) -> Vec<Star> {
-    let y_mut = make_mutation(data, empty_size, |star| star.pos.y);
-    let x_mut = make_mutation(data, empty_size, |star| star.pos.x);
+    let empty_y = empty_before(data.stars.iter().map(|star| star.pos.y));
+    let empty_x = empty_before(data.stars.iter().map(|star| star.pos.x));
 
     data.stars
         .iter()
         .cloned()
         .map(|mut star| {
-            if let Some(margin) = y_mut.get(&star.id) {
-                star.pos.y += margin;
-            }
-            if let Some(margin) = x_mut.get(&star.id) {
-                star.pos.x += margin;
-            }
+            star.pos.y += (empty_size - 1) * empty_y[star.pos.y as usize];
+            star.pos.x += (empty_size - 1) * empty_x[star.pos.x as usize];
             star
         })
         .collect::<Vec<_>>()
 }
 
+/// すべての組の |a - b| の合計。並べ替えると k 番目は k 回足され、n - 1 - k 回引かれる
+pub fn pairwise_sum(values: &mut [Int]) -> Int {
+    values.sort_unstable();
+    let n = values.len() as Int;
+    values
+        .iter()
+        .enumerate()
+        .map(|(k, &v)| v * (2 * k as Int - n + 1))
+        .sum()
+}
+
+/// 膨張率 f のときの距離の合計 constant + slope * f
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DistanceSum {
+    pub constant: Int,
+    pub slope: Int,
+}
+
+impl DistanceSum {
+    pub fn at(&self, empty_size: Int) -> i128 {
+        self.constant as i128 + self.slope as i128 * empty_size as i128
+    }
+}
+
+/// すべての組のマンハッタン距離の合計を、膨張率の1次式で求める。
+/// 膨張後の座標は v + (f - 1) * empty[v] で、v と empty[v] は同じ向きに増えるので
+/// 合計は Σ|Δv| + (f - 1) * Σ|Δempty| になる。
+pub fn distance_sum(data: &InputData) -> DistanceSum {
+    let mut sum = DistanceSum {
+        constant: 0,
+        slope: 0,
+    };
+    for get_pos in [|star: &Star| star.pos.x, |star: &Star| star.pos.y] {
+        let empty = empty_before(data.stars.iter().map(get_pos));
+        let mut coords = data.stars.iter().map(get_pos).collect::<Vec<_>>();
+        let mut empties = coords
+            .iter()
+            .map(|&v| empty[v as usize])
+            .collect::<Vec<_>>();
+
+        let direct = pairwise_sum(&mut coords);
+        let expanded = pairwise_sum(&mut empties);
+        sum.constant += direct - expanded;
+        sum.slope += expanded;
+    }
+    sum
+}
+
 pub fn measure_length(a: &Vec2, b: &Vec2) -> Int {
     let dx = if a.x < b.x { b.x - a.x } else { a.x - b.x };
     let dy = if a.y < b.y { b.y - a.y } else { a.y - b.y };
     dx + dy
 }
 
+/// 星の間の距離の測り方
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Metric {
+    #[default]
+    Manhattan,
+    Chebyshev,
+    Euclidean,
+}
+
+impl Metric {
+    pub fn distance(&self, a: &Vec2, b: &Vec2) -> f64 {
+        let d = (*a - *b).abs();
+        match self {
+            Metric::Manhattan => measure_length(a, b) as f64,
+            Metric::Chebyshev => d.x.max(d.y) as f64,
+            Metric::Euclidean => ((d.x as f64).powi(2) + (d.y as f64).powi(2)).sqrt(),
+        }
+    }
+}
+
+/// 2つの星とその距離
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Pair {
+    pub a: i32,
+    pub b: i32,
+    pub distance: f64,
+}
+
+/// ある星から一番近い星と一番遠い星
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Extremes {
+    pub id: i32,
+    pub nearest: Pair,
+    pub farthest: Pair,
+}
+
+/// 膨張した後の宇宙
+#[derive(Debug, Clone)]
+pub struct Universe {
+    pub stars: Vec<Star>,
+    pub metric: Metric,
+}
+
+impl Universe {
+    pub fn new(data: &InputData, empty_size: Int) -> Self {
+        Universe {
+            stars: make_expanded_stars(data, empty_size),
+            metric: Metric::default(),
+        }
+    }
+
+    pub fn with_metric(self, metric: Metric) -> Self {
+        Universe { metric, ..self }
+    }
+
+    fn pair(&self, i: usize, j: usize) -> Pair {
+        Pair {
+            a: self.stars[i].id,
+            b: self.stars[j].id,
+            distance: self.metric.distance(&self.stars[i].pos, &self.stars[j].pos),
+        }
+    }
+
+    /// すべての組。a の id は b より小さい
+    pub fn pairs(&self) -> impl Iterator<Item = Pair> + '_ {
+        let n = self.stars.len();
+        (0..n).flat_map(move |i| ((i + 1)..n).map(move |j| self.pair(i, j)))
+    }
+
+    /// 星ごとの一番近い星と一番遠い星。同じ距離なら id の小さい方
+    pub fn extremes(&self) -> Vec<Extremes> {
+        (0..self.stars.len())
+            .filter_map(|i| {
+                let others = (0..self.stars.len())
+                    .filter(|&j| j != i)
+                    .map(|j| self.pair(i, j))
+                    .collect::<Vec<_>>();
+                let nearest = *others
+                    .iter()
+                    .min_by(|x, y| x.distance.total_cmp(&y.distance))?;
+                let farthest = *others
+                    .iter()
+                    .rev()
+                    .max_by(|x, y| x.distance.total_cmp(&y.distance))?;
+                Some(Extremes {
+                    id: self.stars[i].id,
+                    nearest,
+                    farthest,
+                })
+            })
+            .collect()
+    }
+
+    /// 距離の短い順に k 組。同じ距離なら id の順
+    pub fn closest_pairs(&self, k: usize) -> Vec<Pair> {
+        let mut pairs = self.pairs().collect::<Vec<_>>();
+        let order = |x: &Pair, y: &Pair| {
+            x.distance
+                .total_cmp(&y.distance)
+                .then((x.a, x.b).cmp(&(y.a, y.b)))
+        };
+        if k < pairs.len() {
+            pairs.select_nth_unstable_by(k, order);
+            pairs.truncate(k);
+        }
+        pairs.sort_by(order);
+        pairs
+    }
+
+    /// 距離の分布。キー i には [i * width, (i + 1) * width) に入る組の数が入る。
+    /// 距離は膨張で大きくなるので、組のない区間は持たない。
+    /// width が正の有限な値でなければ None
+    pub fn histogram(&self, width: f64) -> Option<BTreeMap<u64, usize>> {
+        if !(width.is_finite() && width > 0.0) {
+            return None;
+        }
+
+        let mut bins = BTreeMap::new();
+        for pair in self.pairs() {
+            *bins.entry((pair.distance / width) as u64).or_insert(0) += 1;
+        }
+        Some(bins)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use indoc::indoc;
@@ -151,4 +311,112 @@ mod tests {
         assert_eq!(measure_length(&Vec2::new(0, 0), &Vec2::new(3, 1)), 4);
         assert_eq!(measure_length(&Vec2::new(1, 6), &Vec2::new(5, 11)), 9);
     }
+
+    #[test]
+    fn test_empty_before() {
+        assert_eq!(
+            empty_before([0, 3, 4, 7].into_iter()),
+            [0, 0, 1, 2, 2, 2, 3, 4]
+        );
+        assert!(empty_before(std::iter::empty()).is_empty());
+    }
+
+    #[test]
+    fn test_pairwise_sum() {
+        assert_eq!(pairwise_sum(&mut [3, 0, 1]), 3 + 1 + 2);
+        assert_eq!(pairwise_sum(&mut [5]), 0);
+        assert_eq!(pairwise_sum(&mut []), 0);
+    }
+
+    #[test]
+    fn test_distance_sum() {
+        let data = parse_input(INPUT);
+        let sum = distance_sum(&data);
+
+        assert_eq!(sum.at(2), 374);
+        assert_eq!(sum.at(10), 1030);
+        assert_eq!(sum.at(100), 8410);
+
+        for empty_size in [1, 2, 10, 100, 1_000_000] {
+            let stars = make_expanded_stars(&data, empty_size);
+            let naive = make_pairs(stars.len())
+                .into_iter()
+                .map(|(i, j)| measure_length(&stars[i].pos, &stars[j].pos) as i128)
+                .sum::<i128>();
+            assert_eq!(sum.at(empty_size), naive);
+        }
+
+        // i64 には収まらない
+        assert!(sum.at(1_000_000_000_000_000_000) > i64::MAX as i128);
+    }
+
+    #[test]
+    fn test_universe() {
+        let data = parse_input(INPUT);
+        let universe = Universe::new(&data, 2);
+
+        let extremes = universe.extremes();
+        assert_eq!(extremes.len(), 9);
+        assert_eq!(
+            (extremes[0].nearest.b, extremes[0].nearest.distance),
+            (2, 6.0)
+        );
+        // 6 と 8 はどちらも 15 離れている
+        assert_eq!(
+            (extremes[0].farthest.b, extremes[0].farthest.distance),
+            (6, 15.0)
+        );
+        assert_eq!(
+            (extremes[4].nearest.b, extremes[4].nearest.distance),
+            (3, 5.0)
+        );
+        assert_eq!(
+            (extremes[7].farthest.b, extremes[7].farthest.distance),
+            (2, 19.0)
+        );
+
+        let closest = universe.closest_pairs(4);
+        assert_eq!(
+            closest
+                .iter()
+                .map(|p| (p.a, p.b, p.distance))
+                .collect::<Vec<_>>(),
+            [(2, 4, 5.0), (3, 5, 5.0), (7, 9, 5.0), (8, 9, 5.0)]
+        );
+        assert_eq!(universe.closest_pairs(100).len(), 36);
+
+        assert_eq!(
+            universe.histogram(5.0).unwrap(),
+            BTreeMap::from([(1, 18), (2, 11), (3, 7)])
+        );
+        assert_eq!(universe.histogram(1.0).unwrap().values().sum::<usize>(), 36);
+        for width in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(universe.histogram(width), None, "{}", width);
+        }
+
+        // 膨張が大きくても、組のある区間だけを持つ
+        let data = parse_input("#..\n...\n..#");
+        let universe = Universe::new(&data, 1_000_000_000_000);
+        let histogram = universe.histogram(1.0).unwrap();
+        assert_eq!(histogram, BTreeMap::from([(2_000_000_000_002, 1)]));
+    }
+
+    #[test]
+    fn test_metric() {
+        let (a, b) = (Vec2::new(1, 6), Vec2::new(5, 11));
+        assert_eq!(Metric::Manhattan.distance(&a, &b), 9.0);
+        assert_eq!(Metric::Chebyshev.distance(&a, &b), 5.0);
+        assert_eq!(Metric::Euclidean.distance(&a, &b), 41f64.sqrt());
+
+        let data = parse_input(INPUT);
+        let universe = Universe::new(&data, 2).with_metric(Metric::Euclidean);
+        let extremes = universe.extremes();
+        assert_eq!(
+            (extremes[0].nearest.b, extremes[0].nearest.distance),
+            (3, 20f64.sqrt())
+        );
+
+        let universe = universe.with_metric(Metric::Chebyshev);
+        assert_eq!(universe.closest_pairs(1)[0].distance, 3.0);
+    }
 }
@@ -1,8 +1,7 @@
-use std::collections::HashMap;
 use std::fs;
 use std::io::{BufReader, Read};
 
-use day_12::{arrangements, parse_input};
+use day_12::parse_input;
 
 fn main() -> anyhow::Result<()> {
     let mut r = BufReader::new(fs::File::open("day-12/data/input.txt")?);
@@ -18,14 +17,17 @@ fn main() -> anyhow::Result<()> {
 fn process(input: &str) -> anyhow::Result<String> {
     let (_, data) = parse_input(input).map_err(|e| anyhow::anyhow!("parse_input: {:?}", e))?;
 
-    let mut total = 0;
-    let mut memo = HashMap::new();
+    let mut total = 0u128;
     for (i, criterion) in data.criteria.iter().enumerate() {
         print!("({}/{}) {:?}", i, data.criteria.len(), criterion);
-        let len = arrangements(criterion, &mut memo);
+        let len = criterion
+            .count()
+            .ok_or_else(|| anyhow::anyhow!("too many arrangements: {:?}", criterion))?;
         println!(" -> {}", len);
 
-        total += len;
+        total = total
+            .checked_add(len)
+            .ok_or_else(|| anyhow::anyhow!("total is too large"))?;
     }
 
     Ok(total.to_string())
@@ -50,5 +52,8 @@ mod tests {
     fn test_process() {
         let answer = process(INPUT).unwrap();
         assert_eq!(answer, "21");
+
+        // 読めない行があれば、途中までの合計を答えにしない
+        assert!(process("#. 1\n??? 0\n# 1\n").is_err());
     }
 }
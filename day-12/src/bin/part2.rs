@@ -1,8 +1,7 @@
-use std::collections::HashMap;
 use std::fs;
 use std::io::{BufReader, Read};
 
-use day_12::{arrangements, parse_input, unfold};
+use day_12::{parse_input, unfold};
 
 fn main() -> anyhow::Result<()> {
     let mut r = BufReader::new(fs::File::open("day-12/data/input.txt")?);
@@ -18,16 +17,19 @@ fn main() -> anyhow::Result<()> {
 fn process(input: &str) -> anyhow::Result<String> {
     let (_, data) = parse_input(input).map_err(|e| anyhow::anyhow!("parse_input: {:?}", e))?;
 
-    let mut total = 0;
-    let mut memo = HashMap::new();
+    let mut total = 0u128;
     for (i, criterion) in data.criteria.iter().enumerate() {
         let criterion = unfold(criterion);
 
         print!("({}/{}) {:?}", i, data.criteria.len(), criterion);
-        let len = arrangements(&criterion, &mut memo);
+        let len = criterion
+            .count()
+            .ok_or_else(|| anyhow::anyhow!("too many arrangements: {:?}", criterion))?;
         println!(" -> {}", len);
 
-        total += len;
+        total = total
+            .checked_add(len)
+            .ok_or_else(|| anyhow::anyhow!("total is too large"))?;
     }
 
     Ok(total.to_string())
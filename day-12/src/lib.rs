@@ -1,13 +1,17 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
 
 use itertools::Itertools;
 use nom::bytes::complete::{tag, take_until};
 use nom::character::complete;
-use nom::character::complete::newline;
+use nom::character::complete::{multispace0, newline};
 use nom::character::streaming::space1;
+use nom::combinator::{all_consuming, verify};
 use nom::multi::separated_list1;
+use nom::sequence::terminated;
 use nom::IResult;
+use rand::Rng;
+
+pub mod nonogram;
 
 #[derive(Debug)]
 pub struct InputData<'a> {
@@ -17,14 +21,15 @@ pub struct InputData<'a> {
 #[derive(Debug)]
 pub struct Criterion<'a> {
     springs: Cow<'a, str>,
-    nums: Vec<i32>,
+    nums: Vec<u32>,
 }
 
-pub fn parse_input(input: &str) -> IResult<&str, InputData> {
-    fn criterion(input: &str) -> IResult<&str, Criterion> {
+pub fn parse_input(input: &str) -> IResult<&str, InputData<'_>> {
+    fn criterion(input: &str) -> IResult<&str, Criterion<'_>> {
         let (input, springs) = take_until(" ")(input)?;
         let (input, _) = space1(input)?;
-        let (input, nums) = separated_list1(tag(","), complete::i32)(input)?;
+        // 長さ 0 のグループはない
+        let (input, nums) = separated_list1(tag(","), verify(complete::u32, |&n| n > 0))(input)?;
         Ok((
             input,
             Criterion {
@@ -34,110 +39,354 @@ pub fn parse_input(input: &str) -> IResult<&str, InputData> {
         ))
     }
 
-    let (input, criteria) = separated_list1(newline, criterion)(input)?;
+    // 読めない行を黙って捨てないよう、最後まで読み切る
+    let (input, criteria) =
+        all_consuming(terminated(separated_list1(newline, criterion), multispace0))(input)?;
     Ok((input, InputData { criteria }))
 }
 
 pub fn unfold<'a>(criterion: &Criterion) -> Criterion<'a> {
-    let spring = (0..5).map(|_| criterion.springs.to_string()).join("?");
+    unfold_by(criterion, 5)
+}
 
-    let nums = criterion.nums.repeat(5);
+/// springs を ? でつないで factor 回繰り返す
+pub fn unfold_by<'a>(criterion: &Criterion, factor: usize) -> Criterion<'a> {
+    let spring = (0..factor).map(|_| criterion.springs.to_string()).join("?");
+
+    let nums = criterion.nums.repeat(factor);
     Criterion {
         springs: Cow::from(spring),
         nums,
     }
 }
 
-fn count_char(input: &str, c: &[char]) -> usize {
-    let mut count = 0;
-    for x in input.chars() {
-        if c.iter().any(|&c| c == x) {
-            count += 1;
+impl Criterion<'_> {
+    pub fn springs(&self) -> &str {
+        &self.springs
+    }
+
+    pub fn nums(&self) -> &[u32] {
+        &self.nums
+    }
+
+    /// 条件を満たす並べ方の数。u128 に収まらなければ None
+    pub fn count(&self) -> Option<u128> {
+        Nfa::new(&self.nums).count(&self.springs)
+    }
+
+    /// 条件を満たす並べ方を辞書順（# が先）に1つずつ返す。
+    /// 残りの数え上げが 0 になる枝には入らないので、行き止まりはない。
+    pub fn arrangements(&self) -> Arrangements {
+        let nfa = Nfa::new(&self.nums);
+        let springs = self.springs.as_bytes().to_vec();
+        let live = nfa
+            .backward::<bool>(&springs)
+            .expect("bool never overflows");
+        let stack = if live[0][0] {
+            vec![(0, 0, None)]
         } else {
-            break;
+            vec![]
+        };
+        Arrangements {
+            nfa,
+            prefix: Vec::with_capacity(springs.len()),
+            springs,
+            live,
+            stack,
         }
     }
-    count
-}
 
-pub fn arrangements(criterion: &Criterion, memo: &mut HashMap<(String, Vec<i32>), usize>) -> usize {
-    // remove duplicated dot
-    let mut new_springs = String::new();
-    let mut prev = ' ';
-    for c in criterion.springs.trim_matches('.').chars() {
-        if c == '.' && prev == '.' {
-            continue;
+    /// 条件を満たす並べ方から、一様に1つ選ぶ。並べ方がなければ None。
+    /// 並べ方の数が u128 に収まらなければエラー
+    pub fn sample(&self, rng: &mut impl Rng) -> anyhow::Result<Option<String>> {
+        let nfa = Nfa::new(&self.nums);
+        let springs = self.springs.as_bytes();
+        let ways = nfa
+            .backward::<u128>(springs)
+            .ok_or_else(|| anyhow::anyhow!("too many arrangements to sample"))?;
+        if ways[0][0] == 0 {
+            return Ok(None);
+        }
+
+        let mut state = 0;
+        let mut result = String::with_capacity(springs.len());
+        for (p, &c) in springs.iter().enumerate() {
+            // 選んだ文字の先にある並べ方の数に比例して選ぶ
+            let choices = candidates(c)
+                .iter()
+                .filter_map(|&c| nfa.next(state, c).map(|next| (c, next, ways[p + 1][next])))
+                .collect::<Vec<_>>();
+            let total = choices.iter().map(|&(_, _, w)| w).sum::<u128>();
+            let mut r = rng.gen_range(0..total);
+            let &(c, next, _) = choices
+                .iter()
+                .find(|&&(_, _, w)| {
+                    if r < w {
+                        return true;
+                    }
+                    r -= w;
+                    false
+                })
+                .expect("weights sum to total");
+            result.push(c as char);
+            state = next;
         }
-        new_springs.push(c);
-        prev = c;
+        Ok(Some(result))
     }
 
-    println!("springs {} -> {}", criterion.springs, new_springs);
+    /// ? のマスごとに、# になる並べ方と . になる並べ方の数。u128 に収まらなければ None
+    pub fn cell_counts(&self) -> Option<Vec<CellCount>> {
+        let cells = Nfa::new(&self.nums).cell_ways::<u128>(self.springs.as_bytes())?;
+        let cells = cells
+            .into_iter()
+            .map(|(index, damaged, operational)| CellCount {
+                index,
+                damaged,
+                operational,
+            })
+            .collect();
+        Some(cells)
+    }
+
+    /// どの並べ方でも同じになる ? のマスと、その文字
+    pub fn forced(&self) -> Vec<(usize, char)> {
+        Nfa::new(&self.nums).forced(self.springs.as_bytes())
+    }
+}
+
+/// ? のマスが # になる並べ方と . になる並べ方の数
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CellCount {
+    pub index: usize,
+    pub damaged: u128,
+    pub operational: u128,
+}
 
-    get_arrangements(&new_springs, &criterion.nums, memo)
+/// 経路の数え方。数そのものを数えるか、あるかないかだけを調べる
+trait Ways: Copy + Default + PartialEq {
+    const ONE: Self;
+    fn add(self, other: Self) -> Option<Self>;
+    fn mul(self, other: Self) -> Option<Self>;
 }
 
-fn get_arrangements(
-    spring: &str,
-    nums: &[i32],
-    memo: &mut HashMap<(String, Vec<i32>), usize>,
-) -> usize {
-    let spring = spring.trim_start_matches('.');
-    let key = (spring.to_string(), nums.to_vec());
-    if let Some(&count) = memo.get(&key) {
-        println!("use memoized: {} {:?}", spring, nums);
-        return count;
-    } else {
-        println!("get_arrangements: {} {:?}", spring, nums);
+/// 数が u128 に収まらなければ None
+impl Ways for u128 {
+    const ONE: Self = 1;
+
+    fn add(self, other: Self) -> Option<Self> {
+        self.checked_add(other)
     }
 
-    if nums.is_empty() {
-        return if spring.chars().any(|c| c == '#') {
-            // もうないはずなのに、#があるなら成立していない。
-            0
-        } else {
-            1
-        };
+    fn mul(self, other: Self) -> Option<Self> {
+        self.checked_mul(other)
     }
+}
 
-    let n = nums[0] as usize;
-    let sharp_count = count_char(spring, &['#']);
-    if sharp_count == n {
-        let new_spring = &spring[n..];
-        return if !new_spring.is_empty() && new_spring.starts_with('?') {
-            get_arrangements(&new_spring[1..], &nums[1..], memo)
-        } else {
-            get_arrangements(new_spring, &nums[1..], memo)
-        };
+/// あるかないかだけなので、溢れない
+impl Ways for bool {
+    const ONE: Self = true;
+
+    fn add(self, other: Self) -> Option<Self> {
+        Some(self || other)
+    }
+
+    fn mul(self, other: Self) -> Option<Self> {
+        Some(self && other)
+    }
+}
+
+/// c のマスに置ける文字
+fn candidates(c: u8) -> &'static [u8] {
+    match c {
+        b'?' => b"#.",
+        b'#' => b"#",
+        _ => b".",
+    }
+}
+
+/// [`Criterion::arrangements`] の結果
+#[derive(Debug)]
+pub struct Arrangements {
+    nfa: Nfa,
+    springs: Vec<u8>,
+    /// live[p][i]: 状態 i から p 文字目以降を読んで受理されうるか
+    live: Vec<Vec<bool>>,
+    /// 今たどっている並べ方。戻るときは切り詰める
+    prefix: Vec<u8>,
+    /// (prefix の長さ, 読んだ後の状態, 次に足す文字)
+    stack: Vec<(usize, usize, Option<u8>)>,
+}
+
+impl Iterator for Arrangements {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((len, state, c)) = self.stack.pop() {
+            self.prefix.truncate(len);
+            self.prefix.extend(c);
+            let p = self.prefix.len();
+            if p == self.springs.len() {
+                return Some(String::from_utf8(self.prefix.clone()).expect("ascii"));
+            }
+
+            // 先に試したい文字を後から積む
+            for &c in candidates(self.springs[p]).iter().rev() {
+                if let Some(next) = self.nfa.next(state, c) {
+                    if self.live[p + 1][next] {
+                        self.stack.push((p, next, Some(c)));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// グループの並びを受理する NFA。
+/// 1,3 なら `.#.###.` のように、先頭と各グループの後ろに . を置いた状態の列になる。
+/// . の状態は何回でも繰り返せ、# の状態はちょうど1回だけ通る。
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Nfa {
+    /// states[i]: i 番目の状態が # かどうか
+    states: Vec<bool>,
+}
+
+impl Nfa {
+    pub fn new(nums: &[u32]) -> Self {
+        let mut states = vec![false];
+        for &n in nums {
+            states.extend(std::iter::repeat_n(true, n as usize));
+            states.push(false);
+        }
+        Nfa { states }
     }
 
-    if sharp_count > n {
-        // #が多いなら、成立していない。
-        return 0;
-    } else {
-        let new_spring = &spring[sharp_count..];
-        if new_spring.is_empty() || !new_spring.starts_with('?') {
-            // #が足りない見立てなら、成立しない。
-            return 0;
+    /// 状態 i から c を読んだ次の状態。読めなければ None
+    fn next(&self, i: usize, c: u8) -> Option<usize> {
+        let ahead = self.states.get(i + 1).copied();
+        match c {
+            b'#' => (ahead == Some(true)).then_some(i + 1),
+            _ if !self.states[i] => Some(i),
+            _ => (ahead == Some(false)).then_some(i + 1),
         }
     }
 
-    let mut count = 0;
-    {
-        let replaced = spring.replacen('?', "#", 1);
-        if spring != replaced {
-            count += get_arrangements(&replaced, nums, memo);
+    /// 受理状態かどうか
+    fn accepts(&self, i: usize) -> bool {
+        i + 1 >= self.states.len() - 1
+    }
+
+    /// forward[p][i]: 先頭から p 文字読んで状態 i にいる経路の数
+    fn forward<W: Ways>(&self, springs: &[u8]) -> Option<Vec<Vec<W>>> {
+        let mut table = vec![vec![W::default(); self.states.len()]; springs.len() + 1];
+        table[0][0] = W::ONE;
+        for (p, &c) in springs.iter().enumerate() {
+            for i in 0..self.states.len() {
+                if table[p][i] == W::default() {
+                    continue;
+                }
+                for &c in candidates(c) {
+                    if let Some(next) = self.next(i, c) {
+                        table[p + 1][next] = table[p + 1][next].add(table[p][i])?;
+                    }
+                }
+            }
         }
+        Some(table)
     }
-    {
-        let replaced = spring.replacen('?', ".", 1);
-        if spring != replaced {
-            count += get_arrangements(&replaced, nums, memo);
+
+    /// backward[p][i]: 状態 i から p 文字目以降を読んで受理される経路の数
+    fn backward<W: Ways>(&self, springs: &[u8]) -> Option<Vec<Vec<W>>> {
+        let mut table = vec![vec![W::default(); self.states.len()]; springs.len() + 1];
+        for (i, ways) in table[springs.len()].iter_mut().enumerate() {
+            if self.accepts(i) {
+                *ways = W::ONE;
+            }
+        }
+        for (p, &c) in springs.iter().enumerate().rev() {
+            for i in 0..self.states.len() {
+                table[p][i] = candidates(c)
+                    .iter()
+                    .filter_map(|&c| self.next(i, c))
+                    .try_fold(W::default(), |sum, next| sum.add(table[p + 1][next]))?;
+            }
         }
+        Some(table)
     }
 
-    memo.insert(key, count);
-    count
+    /// ? のマスごとに、(位置, # になる経路, . になる経路)
+    fn cell_ways<W: Ways>(&self, springs: &[u8]) -> Option<Vec<(usize, W, W)>> {
+        let forward = self.forward::<W>(springs)?;
+        let backward = self.backward::<W>(springs)?;
+
+        let ways_with = |p: usize, c: u8| {
+            (0..self.states.len())
+                .filter_map(|i| self.next(i, c).map(|next| (i, next)))
+                .try_fold(W::default(), |sum, (i, next)| {
+                    sum.add(forward[p][i].mul(backward[p + 1][next])?)
+                })
+        };
+        springs
+            .iter()
+            .enumerate()
+            .filter(|(_, &c)| c == b'?')
+            .map(|(index, _)| Some((index, ways_with(index, b'#')?, ways_with(index, b'.')?)))
+            .collect()
+    }
+
+    /// どの経路でも同じ文字になる ? のマスと、その文字
+    pub fn forced(&self, springs: &[u8]) -> Vec<(usize, char)> {
+        self.cell_ways::<bool>(springs)
+            .expect("bool never overflows")
+            .into_iter()
+            .filter_map(
+                |(index, damaged, operational)| match (damaged, operational) {
+                    (true, false) => Some((index, '#')),
+                    (false, true) => Some((index, '.')),
+                    _ => None,
+                },
+            )
+            .collect()
+    }
+
+    /// springs を読み終えたときに受理状態にいる経路の数。u128 に収まらなければ None。
+    /// 残りの文字数で最後まで届かない状態と、まだ届かない状態は飛ばすので、
+    /// 1文字あたりの手間は余裕（文字数 - 必要な最小の長さ）に比例する。
+    pub fn count(&self, springs: &str) -> Option<u128> {
+        let springs = springs.as_bytes();
+        let last = self.states.len() - 1;
+
+        let mut counts = vec![0u128; self.states.len()];
+        counts[0] = 1;
+        for (p, &c) in springs.iter().enumerate() {
+            let remaining = springs.len() - p - 1;
+            let hi = (p + 1).min(last);
+            let lo = last.saturating_sub(remaining + 1);
+
+            // 後ろから更新すれば、1本の配列で済む。i には i か i - 1 からしか来ない
+            for i in (lo..=hi).rev() {
+                let mut ways = 0u128;
+                for &c in candidates(c) {
+                    if self.next(i, c) == Some(i) {
+                        ways = ways.checked_add(counts[i])?;
+                    }
+                    if i > 0 && self.next(i - 1, c) == Some(i) {
+                        ways = ways.checked_add(counts[i - 1])?;
+                    }
+                }
+                counts[i] = ways;
+            }
+            if lo > 0 {
+                counts[lo - 1] = 0;
+            }
+        }
+
+        counts
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| self.accepts(i))
+            .try_fold(0u128, |sum, (_, &ways)| sum.checked_add(ways))
+    }
 }
 
 #[cfg(test)]
@@ -156,21 +405,29 @@ mod tests {
     "#};
 
     #[test]
-    fn test_get_arrangement() {
-        let mut memo = HashMap::new();
-
-        assert_eq!(get_arrangements("?", &[1], &mut memo), 1);
-        assert_eq!(get_arrangements("#", &[1], &mut memo), 1);
-        assert_eq!(get_arrangements(".", &[1], &mut memo), 0);
-        assert_eq!(get_arrangements("..", &[1], &mut memo), 0);
-        assert_eq!(get_arrangements("#.", &[1], &mut memo), 1);
-        assert_eq!(get_arrangements(".#", &[1], &mut memo), 1);
-        assert_eq!(get_arrangements("??", &[1], &mut memo), 2);
-        assert_eq!(get_arrangements("?.?", &[1, 1], &mut memo), 1);
-        assert_eq!(get_arrangements("#.?", &[1, 1], &mut memo), 1);
-        assert_eq!(get_arrangements("?.#", &[1, 1], &mut memo), 1);
-        assert_eq!(get_arrangements("???", &[1, 1], &mut memo), 1);
-        assert_eq!(get_arrangements("???.###", &[1, 1, 3], &mut memo), 1);
+    fn test_nfa_count() {
+        let count = |springs: &str, nums: &[u32]| Nfa::new(nums).count(springs).unwrap();
+
+        assert_eq!(count("?", &[1]), 1);
+        assert_eq!(count("#", &[1]), 1);
+        assert_eq!(count(".", &[1]), 0);
+        assert_eq!(count("..", &[1]), 0);
+        assert_eq!(count("#.", &[1]), 1);
+        assert_eq!(count(".#", &[1]), 1);
+        assert_eq!(count("??", &[1]), 2);
+        assert_eq!(count("?.?", &[1, 1]), 1);
+        assert_eq!(count("#.?", &[1, 1]), 1);
+        assert_eq!(count("?.#", &[1, 1]), 1);
+        assert_eq!(count("???", &[1, 1]), 1);
+        assert_eq!(count("???.###", &[1, 1, 3]), 1);
+
+        assert_eq!(count("...", &[]), 1);
+        assert_eq!(count(".#.", &[]), 0);
+        assert_eq!(count("", &[]), 1);
+        assert_eq!(count("", &[1]), 0);
+        assert_eq!(count("##", &[1]), 0);
+        assert_eq!(count("#?#", &[3]), 1);
+        assert_eq!(count("?????", &[1, 1]), 6);
     }
 
     #[test]
@@ -185,27 +442,47 @@ mod tests {
     }
 
     #[test]
-    fn test_arrangements() {
+    fn test_count() {
         let (_, data) = parse_input(INPUT).unwrap();
 
-        let mut memo = HashMap::new();
-        assert_eq!(arrangements(&data.criteria[0], &mut memo), 1);
-        assert_eq!(arrangements(&data.criteria[1], &mut memo), 4);
-        assert_eq!(arrangements(&data.criteria[2], &mut memo), 1);
-        assert_eq!(arrangements(&data.criteria[3], &mut memo), 1);
-        assert_eq!(arrangements(&data.criteria[4], &mut memo), 4);
-        assert_eq!(arrangements(&data.criteria[5], &mut memo), 10);
+        let counts = data
+            .criteria
+            .iter()
+            .map(|c| c.count().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [1, 4, 1, 1, 4, 10]);
     }
 
     #[test]
     fn test_make_patterns_unfold_len() {
         let (_, data) = parse_input(INPUT).unwrap();
-        let mut memo = HashMap::new();
-        let criterion = unfold(&data.criteria[0]);
-        assert_eq!(arrangements(&criterion, &mut memo), 1);
 
-        let criterion = unfold(&data.criteria[3]);
-        assert_eq!(arrangements(&criterion, &mut memo), 16);
+        let counts = data
+            .criteria
+            .iter()
+            .map(|c| unfold(c).count().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [1, 16384, 1, 16, 2500, 506250]);
+    }
+
+    #[test]
+    fn test_unfold_large() {
+        let (_, data) = parse_input(INPUT).unwrap();
+
+        // 1 通りしかない行は、何回繰り返しても 1 通り
+        let criterion = unfold_by(&data.criteria[0], 1000);
+        assert_eq!(criterion.springs().len(), 8 * 1000 - 1);
+        assert_eq!(criterion.count(), Some(1));
+
+        // ????.#...#... 4,1,1 は繰り返すごとに 2 倍になる
+        assert_eq!(unfold_by(&data.criteria[3], 100).count(), Some(1 << 99));
+        assert_eq!(unfold_by(&data.criteria[3], 128).count(), Some(1 << 127));
+        // 2^129 は u128 に収まらない
+        assert_eq!(unfold_by(&data.criteria[3], 130).count(), None);
+
+        // u128 に収まる範囲で、5 回のときより大きい
+        let criterion = unfold_by(&data.criteria[1], 20);
+        assert!(criterion.count() > unfold(&data.criteria[1]).count());
     }
 
     #[test]
@@ -219,5 +496,142 @@ mod tests {
 
         assert_eq!(data.criteria[5].springs, "?###????????");
         assert_eq!(data.criteria[5].nums, [3, 2, 1]);
+
+        // 長さが 0 以下のグループは読めない
+        assert!(parse_input("??? 1,-1").is_err());
+        assert!(parse_input("??? 0").is_err());
+        // 途中の行が読めなければ、残りを捨てずにエラーにする
+        assert!(parse_input("#. 1\n??? 0\n# 1\n").is_err());
+    }
+
+    /// 並べ方が条件を満たすか
+    fn is_valid(criterion: &Criterion, arrangement: &str) -> bool {
+        let fits = criterion
+            .springs()
+            .chars()
+            .zip(arrangement.chars())
+            .all(|(s, a)| s == '?' || s == a);
+        let groups = arrangement
+            .split('.')
+            .filter(|g| !g.is_empty())
+            .map(|g| g.len() as u32)
+            .collect::<Vec<_>>();
+        fits && arrangement.len() == criterion.springs().len() && groups == criterion.nums()
+    }
+
+    #[test]
+    fn test_arrangements() {
+        let (_, data) = parse_input(INPUT).unwrap();
+
+        assert_eq!(
+            data.criteria[0].arrangements().collect::<Vec<_>>(),
+            ["#.#.###"]
+        );
+        assert_eq!(
+            data.criteria[1].arrangements().collect::<Vec<_>>(),
+            [
+                ".#...#....###.",
+                ".#....#...###.",
+                "..#..#....###.",
+                "..#...#...###.",
+            ]
+        );
+
+        for criterion in data
+            .criteria
+            .iter()
+            .map(unfold)
+            .chain(data.criteria.iter().map(|c| unfold_by(c, 1)))
+        {
+            if criterion.count().unwrap() > 10_000 {
+                continue;
+            }
+            let all = criterion.arrangements().collect::<Vec<_>>();
+            assert_eq!(Some(all.len() as u128), criterion.count());
+            assert!(all.iter().all(|a| is_valid(&criterion, a)));
+            assert!(all.windows(2).all(|w| w[0] < w[1]));
+        }
+
+        // 数が u128 に収まらなくても、先頭から順に取り出せる
+        let criterion = unfold_by(&data.criteria[3], 130);
+        let first = criterion.arrangements().take(2).collect::<Vec<_>>();
+        assert!(first.iter().all(|a| is_valid(&criterion, a)));
+        assert!(first[0] < first[1]);
+
+        let (_, data) = parse_input("#.# 3").unwrap();
+        assert_eq!(data.criteria[0].arrangements().count(), 0);
+    }
+
+    #[test]
+    fn test_sample() {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let (_, data) = parse_input(INPUT).unwrap();
+        let mut rng = StdRng::seed_from_u64(12);
+
+        let criterion = &data.criteria[1];
+        let all = criterion.arrangements().collect::<Vec<_>>();
+        let mut counts = vec![0; all.len()];
+        for _ in 0..4000 {
+            let sample = criterion.sample(&mut rng).unwrap().unwrap();
+            counts[all.iter().position(|a| *a == sample).unwrap()] += 1;
+        }
+        // 4 通りがそれぞれ 1000 回前後
+        assert!(
+            counts.iter().all(|&c| (850..1150).contains(&c)),
+            "{:?}",
+            counts
+        );
+
+        let criterion = unfold(&data.criteria[5]);
+        for _ in 0..10 {
+            assert!(is_valid(
+                &criterion,
+                &criterion.sample(&mut rng).unwrap().unwrap()
+            ));
+        }
+
+        let (_, data) = parse_input("#.# 3").unwrap();
+        assert_eq!(data.criteria[0].sample(&mut rng).unwrap(), None);
+
+        // 並べ方が u128 に収まらない
+        let (_, data) = parse_input(INPUT).unwrap();
+        assert!(unfold_by(&data.criteria[3], 130).sample(&mut rng).is_err());
+    }
+
+    #[test]
+    fn test_cell_counts() {
+        let (_, data) = parse_input(INPUT).unwrap();
+
+        // ?###???????? 3,2,1
+        let criterion = &data.criteria[5];
+        let cells = criterion.cell_counts().unwrap();
+        assert_eq!(cells.len(), 9);
+        assert!(cells.iter().all(|c| c.damaged + c.operational == 10));
+        assert_eq!(criterion.forced(), [(0, '.'), (4, '.')]);
+
+        // .??..??...?##. 1,1,3 の最後の ? は、3 に含まれないといけない
+        assert_eq!(data.criteria[1].forced(), [(10, '#')]);
+
+        // 繰り返したものでも、数え上げと合う
+        let criterion = unfold(&data.criteria[3]);
+        let all = criterion.arrangements().collect::<Vec<_>>();
+        for cell in criterion.cell_counts().unwrap() {
+            let damaged = all
+                .iter()
+                .filter(|a| a.as_bytes()[cell.index] == b'#')
+                .count();
+            assert_eq!(cell.damaged, damaged as u128);
+            assert_eq!(cell.operational, (all.len() - damaged) as u128);
+        }
+
+        // 数は u128 に収まらなくても、決まるマスは求められる
+        let criterion = unfold_by(&data.criteria[3], 130);
+        assert_eq!(criterion.cell_counts(), None);
+        assert_eq!(
+            criterion.forced()[..4],
+            [(0, '#'), (1, '#'), (2, '#'), (3, '#')]
+        );
     }
 }
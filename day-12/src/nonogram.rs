@@ -0,0 +1,338 @@
+//! ノノグラム（お絵かきロジック）。
+//! 1行ぶんの判定は、ばねの並べ方を数える [`Nfa`] そのもの。
+
+use std::fmt;
+
+use super::Nfa;
+
+/// 行と列のヒント
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Puzzle {
+    pub rows: Vec<Vec<u32>>,
+    pub columns: Vec<Vec<u32>>,
+}
+
+/// 盤面。マスは # (塗る)、. (塗らない)、? (未定)
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Solution {
+    Unique(Grid),
+    /// 見つかった解のうち2つ
+    Multiple(Grid, Grid),
+    Contradiction,
+}
+
+impl Grid {
+    fn new(width: usize, height: usize) -> Self {
+        Grid {
+            width,
+            height,
+            cells: vec![b'?'; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> char {
+        self.cells[y * self.width + x] as char
+    }
+
+    fn row(&self, y: usize) -> Vec<u8> {
+        self.cells[y * self.width..(y + 1) * self.width].to_vec()
+    }
+
+    fn column(&self, x: usize) -> Vec<u8> {
+        (0..self.height)
+            .map(|y| self.cells[y * self.width + x])
+            .collect()
+    }
+
+    fn is_solved(&self) -> bool {
+        !self.cells.contains(&b'?')
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 列がなくても行の数だけ空の行を書く
+        for y in 0..self.height {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            let row = &self.cells[y * self.width..(y + 1) * self.width];
+            write!(f, "{}", String::from_utf8_lossy(row))?;
+        }
+        Ok(())
+    }
+}
+
+/// 1行ぶんを解く。決まったマスを埋めて、矛盾していれば None
+fn solve_line(clue: &[u32], line: &[u8]) -> Option<Vec<u8>> {
+    let nfa = Nfa::new(clue);
+    // u128 に収まらないほどあれば、少なくとも矛盾はしていない
+    if nfa.count(std::str::from_utf8(line).expect("ascii")) == Some(0) {
+        return None;
+    }
+
+    let mut line = line.to_vec();
+    for (index, c) in nfa.forced(&line) {
+        line[index] = c as u8;
+    }
+    Some(line)
+}
+
+impl Puzzle {
+    /// `.non` 形式を読む。
+    /// `width` / `height` と、`rows` / `columns` の後にヒントを1行ずつ並べたもの。
+    /// ヒントは , か空白で区切り、空の行は 0 と書く。それ以外の項目は読み飛ばす。
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        enum Section {
+            None,
+            Rows,
+            Columns,
+        }
+
+        let (mut width, mut height) = (None, None);
+        let (mut rows, mut columns) = (Vec::new(), Vec::new());
+        let mut section = Section::None;
+        for line in input.lines().map(str::trim) {
+            let keyword = line.split_whitespace().next().unwrap_or("");
+            match keyword {
+                "" => section = Section::None,
+                "rows" => section = Section::Rows,
+                "columns" => section = Section::Columns,
+                "width" | "height" => {
+                    let value = line[keyword.len()..].trim().parse::<usize>()?;
+                    if keyword == "width" {
+                        width = Some(value);
+                    } else {
+                        height = Some(value);
+                    }
+                    section = Section::None;
+                }
+                _ if line.starts_with(|c: char| c.is_ascii_digit()) => {
+                    let clue = line
+                        .split([',', ' '])
+                        .filter(|n| !n.is_empty())
+                        .map(|n| n.parse::<u32>())
+                        .filter(|n| !matches!(n, Ok(0)))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    match section {
+                        Section::Rows => rows.push(clue),
+                        Section::Columns => columns.push(clue),
+                        Section::None => anyhow::bail!("clue outside rows/columns: {}", line),
+                    }
+                }
+                _ => section = Section::None,
+            }
+        }
+
+        if width.is_some_and(|w| w != columns.len()) {
+            anyhow::bail!("width is {:?} but {} columns", width, columns.len());
+        }
+        if height.is_some_and(|h| h != rows.len()) {
+            anyhow::bail!("height is {:?} but {} rows", height, rows.len());
+        }
+
+        // グループの間には1マス以上空ける
+        let fits = |clue: &[u32], len: usize| {
+            clue.iter().map(|&n| n as u64).sum::<u64>() + clue.len().saturating_sub(1) as u64
+                <= len as u64
+        };
+        if let Some(clue) = rows.iter().find(|clue| !fits(clue, columns.len())) {
+            anyhow::bail!("row clue {:?} does not fit width {}", clue, columns.len());
+        }
+        if let Some(clue) = columns.iter().find(|clue| !fits(clue, rows.len())) {
+            anyhow::bail!("column clue {:?} does not fit height {}", clue, rows.len());
+        }
+        Ok(Puzzle { rows, columns })
+    }
+
+    pub fn solve(&self) -> Solution {
+        let mut found = Vec::new();
+        self.search(Grid::new(self.columns.len(), self.rows.len()), &mut found);
+
+        let mut found = found.into_iter();
+        match (found.next(), found.next()) {
+            (Some(a), Some(b)) => Solution::Multiple(a, b),
+            (Some(a), None) => Solution::Unique(a),
+            _ => Solution::Contradiction,
+        }
+    }
+
+    /// 変わらなくなるまで行と列を解く。矛盾していれば false
+    fn propagate(&self, grid: &mut Grid) -> bool {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (y, clue) in self.rows.iter().enumerate() {
+                let before = grid.row(y);
+                let Some(after) = solve_line(clue, &before) else {
+                    return false;
+                };
+                if after != before {
+                    grid.cells[y * grid.width..(y + 1) * grid.width].copy_from_slice(&after);
+                    changed = true;
+                }
+            }
+            for (x, clue) in self.columns.iter().enumerate() {
+                let before = grid.column(x);
+                let Some(after) = solve_line(clue, &before) else {
+                    return false;
+                };
+                if after != before {
+                    for (y, &c) in after.iter().enumerate() {
+                        grid.cells[y * grid.width + x] = c;
+                    }
+                    changed = true;
+                }
+            }
+        }
+        true
+    }
+
+    /// 行き詰まったら未定のマスを1つ決めて試す。解が2つ見つかったらやめる
+    fn search(&self, mut grid: Grid, found: &mut Vec<Grid>) {
+        if found.len() >= 2 || !self.propagate(&mut grid) {
+            return;
+        }
+        let Some(index) = grid.cells.iter().position(|&c| c == b'?') else {
+            debug_assert!(grid.is_solved());
+            found.push(grid);
+            return;
+        };
+
+        for c in [b'#', b'.'] {
+            let mut next = grid.clone();
+            next.cells[index] = c;
+            self.search(next, found);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    const NON: &str = indoc! {r#"
+    catalogue "example"
+    title "Letter P"
+    width 5
+    height 5
+
+    rows
+    4
+    1,1
+    4
+    1
+    1
+
+    columns
+    5
+    1,1
+    1,1
+    3
+    0
+
+    goal "1111010010111101000010000"
+    "#};
+
+    #[test]
+    fn test_parse() {
+        let puzzle = Puzzle::parse(NON).unwrap();
+        assert_eq!(
+            puzzle.rows,
+            [vec![4], vec![1, 1], vec![4], vec![1], vec![1]]
+        );
+        assert_eq!(
+            puzzle.columns,
+            [vec![5], vec![1, 1], vec![1, 1], vec![3], vec![]]
+        );
+
+        assert!(Puzzle::parse("width 2\n\nrows\n1\n\ncolumns\n1\n").is_err());
+        assert!(Puzzle::parse("1,1\n").is_err());
+
+        // 負のヒントは読めない
+        assert!(Puzzle::parse("rows\n1,-1\n1\n\ncolumns\n1\n1\n").is_err());
+        // 行と列の長さに収まらない
+        assert!(Puzzle::parse("rows\n1,1\n1\n\ncolumns\n1\n1\n").is_err());
+        assert!(Puzzle::parse("rows\n1\n1\n\ncolumns\n3\n1\n").is_err());
+        assert!(Puzzle::parse("rows\n4294967295,4294967295\n\ncolumns\n1\n").is_err());
+        assert!(Puzzle::parse("rows\n2\n1\n\ncolumns\n2\n1\n").is_ok());
+    }
+
+    #[test]
+    fn test_solve_unique() {
+        let puzzle = Puzzle::parse(NON).unwrap();
+        let Solution::Unique(grid) = puzzle.solve() else {
+            panic!("should be unique");
+        };
+
+        let expected = indoc! {r#"
+        ####.
+        #..#.
+        ####.
+        #....
+        #...."#};
+        assert_eq!(grid.to_string(), expected);
+        assert_eq!(grid.get(3, 1), '#');
+    }
+
+    #[test]
+    fn test_solve_multiple() {
+        // 対角線のどちらでもよい
+        let puzzle = Puzzle {
+            rows: vec![vec![1], vec![1]],
+            columns: vec![vec![1], vec![1]],
+        };
+        let Solution::Multiple(a, b) = puzzle.solve() else {
+            panic!("should be multiple");
+        };
+        assert_eq!(a.to_string(), "#.\n.#");
+        assert_eq!(b.to_string(), ".#\n#.");
+    }
+
+    #[test]
+    fn test_solve_contradiction() {
+        let puzzle = Puzzle {
+            rows: vec![vec![2], vec![]],
+            columns: vec![vec![1], vec![]],
+        };
+        assert_eq!(puzzle.solve(), Solution::Contradiction);
+    }
+
+    #[test]
+    fn test_solve_no_columns() {
+        let puzzle = Puzzle {
+            rows: vec![vec![], vec![]],
+            columns: vec![],
+        };
+        let Solution::Unique(grid) = puzzle.solve() else {
+            panic!("should be unique");
+        };
+        assert_eq!(grid.to_string(), "\n");
+    }
+
+    #[test]
+    fn test_solve_backtracking() {
+        // 下の2行は1行ずつ解いても決まらないが、試すと1つに決まる
+        let puzzle = Puzzle {
+            rows: vec![vec![4], vec![], vec![1, 1], vec![2]],
+            columns: vec![vec![1, 1]; 4],
+        };
+        let mut grid = Grid::new(4, 4);
+        assert!(puzzle.propagate(&mut grid));
+        assert_eq!(grid.to_string(), "####\n....\n????\n????");
+
+        let Solution::Unique(grid) = puzzle.solve() else {
+            panic!("should be unique");
+        };
+        assert_eq!(grid.to_string(), "####\n....\n#..#\n.##.");
+    }
+}
@@ -1 +1,383 @@
+use std::fmt;
 
+use glam::{uvec2, UVec2};
+use nom::bytes::complete::is_a;
+use nom::character::complete::{multispace0, newline};
+use nom::combinator::all_consuming;
+use nom::multi::separated_list1;
+use nom::sequence::{terminated, tuple};
+use nom::IResult;
+
+/// # を 1 としたビット列で持つ模様。
+/// rows[y] の x ビット目と columns[x] の y ビット目が同じマスを表す。
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Pattern {
+    pub size: UVec2,
+    pub rows: Vec<u64>,
+    pub columns: Vec<u64>,
+}
+
+/// 反射の軸
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Axis {
+    /// 上にある行の数
+    Horizontal(u32),
+    /// 左にある列の数
+    Vertical(u32),
+}
+
+impl Axis {
+    /// 縦の軸は左の列の数、横の軸は上の行の数の 100 倍
+    pub fn summary(&self) -> u32 {
+        match self {
+            Axis::Horizontal(n) => n * 100,
+            Axis::Vertical(n) => *n,
+        }
+    }
+}
+
+/// 行と列は 1 から数える
+impl fmt::Display for Axis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Axis::Horizontal(n) => write!(f, "horizontal between rows {} and {}", n, n + 1),
+            Axis::Vertical(n) => write!(f, "vertical between columns {} and {}", n, n + 1),
+        }
+    }
+}
+
+/// 軸と、そこで折り返したときに重なって違うマスの組
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AxisReport {
+    pub axis: Axis,
+    pub mismatches: u32,
+    /// どちらか一方を反転すれば揃うマスの組。0 から数えた (x, y)
+    pub cells: Vec<(UVec2, UVec2)>,
+}
+
+/// Axis と同じく、行と列は 1 から数える
+impl fmt::Display for AxisReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural = if self.mismatches == 1 { "" } else { "es" };
+        write!(f, "{}: {} mismatch{}", self.axis, self.mismatches, plural)?;
+        for (a, b) in &self.cells {
+            write!(
+                f,
+                ", flip row {} column {} or row {} column {}",
+                a.y + 1,
+                a.x + 1,
+                b.y + 1,
+                b.x + 1
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct InputData {
+    pub patterns: Vec<Pattern>,
+}
+
+impl Pattern {
+    pub fn new(lines: &[&str]) -> anyhow::Result<Self> {
+        let Some(first) = lines.first() else {
+            anyhow::bail!("pattern is empty");
+        };
+        let size = uvec2(first.len() as u32, lines.len() as u32);
+        if size.x > u64::BITS || size.y > u64::BITS {
+            anyhow::bail!("pattern is too large: {}", size);
+        }
+
+        let mut rows = vec![0u64; size.y as usize];
+        let mut columns = vec![0u64; size.x as usize];
+        for (y, line) in lines.iter().enumerate() {
+            if line.len() != size.x as usize {
+                anyhow::bail!("line {} has a different width", y);
+            }
+            for (x, c) in line.bytes().enumerate() {
+                if c == b'#' {
+                    rows[y] |= 1 << x;
+                    columns[x] |= 1 << y;
+                }
+            }
+        }
+
+        Ok(Pattern {
+            size,
+            rows,
+            columns,
+        })
+    }
+
+    /// 違うマスがちょうど smudges 個になる軸。横の軸を先に探す
+    pub fn reflection(&self, smudges: u32) -> Option<Axis> {
+        if let Some(n) = find_axis(&self.rows, smudges) {
+            return Some(Axis::Horizontal(n));
+        }
+        find_axis(&self.columns, smudges).map(Axis::Vertical)
+    }
+
+    /// すべての横の軸と縦の軸
+    pub fn axes(&self) -> Vec<AxisReport> {
+        let horizontal = (1..self.size.y).map(Axis::Horizontal);
+        let vertical = (1..self.size.x).map(Axis::Vertical);
+        horizontal
+            .chain(vertical)
+            .map(|axis| {
+                let cells = self.mismatched_cells(axis);
+                AxisReport {
+                    axis,
+                    mismatches: cells.len() as u32,
+                    cells,
+                }
+            })
+            .collect()
+    }
+
+    /// 違うマスがちょうど smudges 個の軸
+    pub fn fixes(&self, smudges: u32) -> Vec<AxisReport> {
+        self.axes()
+            .into_iter()
+            .filter(|report| report.mismatches == smudges)
+            .collect()
+    }
+
+    /// axis で折り返したときに重なって違うマスの組
+    pub fn mismatched_cells(&self, axis: Axis) -> Vec<(UVec2, UVec2)> {
+        let (lines, at) = match axis {
+            Axis::Horizontal(n) => (&self.rows, n),
+            Axis::Vertical(n) => (&self.columns, n),
+        };
+        // (線の番号, 線の中の位置) を (x, y) にする
+        let cell = |line: u32, bit: u32| match axis {
+            Axis::Horizontal(_) => uvec2(bit, line),
+            Axis::Vertical(_) => uvec2(line, bit),
+        };
+
+        let mut cells = Vec::new();
+        for (a, b) in (0..at).rev().zip(at..lines.len() as u32) {
+            let mut diff = lines[a as usize] ^ lines[b as usize];
+            while diff != 0 {
+                let bit = diff.trailing_zeros();
+                cells.push((cell(a, bit), cell(b, bit)));
+                diff &= diff - 1;
+            }
+        }
+        cells
+    }
+}
+
+/// lines の at 本目の手前で折り返したときに、重なって違うマスの数
+pub fn mismatches(lines: &[u64], at: usize) -> u32 {
+    let (before, after) = lines.split_at(at);
+    before
+        .iter()
+        .rev()
+        .zip(after)
+        .map(|(a, b)| (a ^ b).count_ones())
+        .sum()
+}
+
+/// 違うマスがちょうど smudges 個になる、最初の折り返し位置
+pub fn find_axis(lines: &[u64], smudges: u32) -> Option<u32> {
+    (1..lines.len())
+        .find(|&at| mismatches(lines, at) == smudges)
+        .map(|at| at as u32)
+}
+
+/// 模様を読む。模様が大きすぎるなどの理由は、1 から数えた模様の番号とともにエラーのメッセージに残す
+pub fn parse_input(input: &str) -> anyhow::Result<InputData> {
+    fn groups(input: &str) -> IResult<&str, Vec<Vec<&str>>> {
+        let pattern = separated_list1(newline, is_a(".#"));
+        let groups = separated_list1(tuple((newline, newline)), pattern);
+        // 読めない文字があれば、そこまでで打ち切らずにエラーにする
+        all_consuming(terminated(groups, multispace0))(input)
+    }
+
+    let (_, groups) = groups(input).map_err(|e| anyhow::anyhow!("{:?}", e))?;
+    let patterns = groups
+        .iter()
+        .enumerate()
+        .map(|(i, lines)| {
+            Pattern::new(lines).map_err(|e| anyhow::anyhow!("pattern {}: {}", i + 1, e))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(InputData { patterns })
+}
+
+/// すべての模様の、汚れが smudges 個の軸の合計
+pub fn summarize(data: &InputData, smudges: u32) -> anyhow::Result<u32> {
+    data.patterns
+        .iter()
+        .map(|p| {
+            p.reflection(smudges)
+                .map(|axis| axis.summary())
+                .ok_or_else(|| anyhow::anyhow!("no reflection: {:?}", p))
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    const INPUT: &str = indoc! {r#"
+    #.##..##.
+    ..#.##.#.
+    ##......#
+    ##......#
+    ..#.##.#.
+    ..##..##.
+    #.#.##.#.
+
+    #...##..#
+    #....#..#
+    ..##..###
+    #####.##.
+    #####.##.
+    ..##..###
+    #....#..#
+    "#};
+
+    #[test]
+    fn test_parse_input() {
+        let data = parse_input(INPUT).unwrap();
+
+        assert_eq!(data.patterns.len(), 2);
+        assert_eq!(data.patterns[0].size, uvec2(9, 7));
+        assert_eq!(data.patterns[1].size, uvec2(9, 7));
+
+        // #.##..##. は 0, 2, 3, 6, 7 ビット目
+        assert_eq!(data.patterns[0].rows[0], 0b011001101);
+        // 1 列目は #.##..# を縦に読んだもの
+        assert_eq!(data.patterns[0].columns[0], 0b1001101);
+
+        // 読めない理由がメッセージに残る
+        let wide = format!("{}\n\n{}", "#.", "#".repeat(65));
+        let e = parse_input(&wide).unwrap_err().to_string();
+        assert!(e.contains("pattern 2") && e.contains("too large"), "{}", e);
+        let e = parse_input("#.\n#").unwrap_err().to_string();
+        assert!(e.contains("different width"), "{}", e);
+
+        assert!(Pattern::new(&[]).is_err());
+
+        // 後ろの模様に読めない文字がある
+        assert!(parse_input("#.\n.#\n\n#x\n..\n").is_err());
+    }
+
+    #[test]
+    fn test_mismatches() {
+        let data = parse_input(INPUT).unwrap();
+
+        let p = &data.patterns[0];
+        assert_eq!(mismatches(&p.columns, 5), 0);
+        assert_eq!(mismatches(&p.rows, 3), 1);
+        // #.##..##. と ..#.##.#. は 5 マス違う
+        assert_eq!(mismatches(&p.rows, 1), 5);
+
+        let p = &data.patterns[1];
+        assert_eq!(mismatches(&p.rows, 4), 0);
+        assert_eq!(mismatches(&p.rows, 1), 1);
+    }
+
+    #[test]
+    fn test_reflection() {
+        let data = parse_input(INPUT).unwrap();
+
+        let p = &data.patterns[0];
+        assert_eq!(p.reflection(0), Some(Axis::Vertical(5)));
+        assert_eq!(p.reflection(1), Some(Axis::Horizontal(3)));
+
+        let p = &data.patterns[1];
+        assert_eq!(p.reflection(0), Some(Axis::Horizontal(4)));
+        assert_eq!(p.reflection(1), Some(Axis::Horizontal(1)));
+
+        assert_eq!(summarize(&data, 0).unwrap(), 405);
+        assert_eq!(summarize(&data, 1).unwrap(), 400);
+    }
+
+    #[test]
+    fn test_reflection_smudges() {
+        // 2 マス違えば、汚れが 2 個の軸になる
+        let data = parse_input("##.\n...\n##.").unwrap();
+        let p = &data.patterns[0];
+        assert_eq!(find_axis(&p.rows, 2), Some(1));
+        assert_eq!(p.reflection(2), Some(Axis::Horizontal(1)));
+        assert_eq!(p.reflection(0), Some(Axis::Vertical(1)));
+        assert_eq!(p.reflection(5), None);
+    }
+
+    #[test]
+    fn test_axes() {
+        let data = parse_input(INPUT).unwrap();
+
+        let p = &data.patterns[0];
+        let axes = p.axes();
+        assert_eq!(axes.len(), 6 + 8);
+        for report in &axes {
+            let lines = match report.axis {
+                Axis::Horizontal(_) => &p.rows,
+                Axis::Vertical(_) => &p.columns,
+            };
+            let at = match report.axis {
+                Axis::Horizontal(n) | Axis::Vertical(n) => n as usize,
+            };
+            assert_eq!(report.mismatches, mismatches(lines, at));
+        }
+
+        assert_eq!(
+            p.fixes(0),
+            [AxisReport {
+                axis: Axis::Vertical(5),
+                mismatches: 0,
+                cells: vec![],
+            }]
+        );
+        // 左上の汚れ
+        assert_eq!(
+            p.fixes(1),
+            [AxisReport {
+                axis: Axis::Horizontal(3),
+                mismatches: 1,
+                cells: vec![(uvec2(0, 0), uvec2(0, 5))],
+            }]
+        );
+
+        let p = &data.patterns[1];
+        assert_eq!(
+            p.fixes(1),
+            [AxisReport {
+                axis: Axis::Horizontal(1),
+                mismatches: 1,
+                cells: vec![(uvec2(4, 0), uvec2(4, 1))],
+            }]
+        );
+        assert_eq!(
+            p.fixes(1)[0].to_string(),
+            "horizontal between rows 1 and 2: 1 mismatch, flip row 1 column 5 or row 2 column 5"
+        );
+        assert_eq!(
+            data.patterns[0].fixes(0)[0].to_string(),
+            "vertical between columns 5 and 6: 0 mismatches"
+        );
+    }
+
+    #[test]
+    fn test_mismatched_cells_vertical() {
+        // 1 列目と 2 列目で、2 行目だけ違う
+        let data = parse_input("##\n#.").unwrap();
+        let p = &data.patterns[0];
+
+        assert_eq!(
+            p.mismatched_cells(Axis::Vertical(1)),
+            [(uvec2(0, 1), uvec2(1, 1))]
+        );
+        assert_eq!(
+            p.mismatched_cells(Axis::Horizontal(1)),
+            [(uvec2(1, 0), uvec2(1, 1))]
+        );
+    }
+}